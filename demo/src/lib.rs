@@ -2,13 +2,142 @@
 
 use new_array::NewArray;
 
-#[derive(NewArray, Clone)]
+#[derive(NewArray)]
 #[new_array(derive(
-    Default, PartialEq, Eq, PartialOrd, Ord, Hash, AsRef, AsMut, From, Into, Borrow, BorrowMut,
-    Debug, Drop
+    Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, AsRef, AsMut, From, Into, TryFrom,
+    Borrow, BorrowMut, Debug, LowerHex, UpperHex, Drop
 ))]
-#[new_array(derive_with_deps(Display))]
+#[new_array(derive(Index, IndexMut, IntoIterator), methods)]
+#[new_array(
+    derive_with_deps(Display, FromStr, Serialize, Deserialize),
+    display = "short_hex"
+)]
 pub struct ByteN(pub [u8; 33]);
 
-#[derive(Debug)]
-pub struct Byte2([u8; 2]);
+#[derive(NewArray)]
+#[new_array(derive(Clone, Copy, Default, PartialEq, Eq, Debug, LowerHex))]
+#[new_array(derive_with_deps(Display))]
+pub struct Byte2(pub [u8; 2]);
+
+#[derive(NewArray)]
+#[new_array(derive(Default, PartialEq, Eq, Debug))]
+#[new_array(derive_with_deps(Display, FromStr))]
+pub struct Empty(pub [u8; 0]);
+
+#[derive(NewArray)]
+#[new_array(derive(
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    AsRef,
+    AsMut,
+    From,
+    Into,
+    Borrow,
+    BorrowMut,
+    Debug,
+    Deref,
+    DerefMut,
+    IntoIterator
+))]
+#[new_array(derive_with_deps(Serialize, Deserialize), deref = "array", methods)]
+pub struct Limbs(pub [u64; 40]);
+
+#[derive(NewArray)]
+#[new_array(derive(Default, PartialEq, Eq, Hash, AsRef, From, Into, Debug))]
+pub struct Flags(pub [bool; 64]);
+
+pub const HASH_LEN: usize = 48;
+
+#[derive(NewArray)]
+#[new_array(derive(Default, PartialEq, Eq, Hash, AsRef, From, Into, Debug))]
+#[new_array(derive_with_deps(Display, FromStr))]
+pub struct Hash(pub [u8; HASH_LEN]);
+
+#[derive(NewArray)]
+#[new_array(derive(Default, PartialEq, Eq, AsRef, Debug))]
+pub struct DoubleHash(pub [u8; HASH_LEN * 2]);
+
+#[derive(NewArray)]
+#[new_array(derive(
+    Default, PartialEq, Eq, PartialOrd, Ord, Hash, AsRef, AsMut, From, Into, TryFrom, Borrow,
+    BorrowMut, Debug, LowerHex, UpperHex, Deref, DerefMut, FromRef
+))]
+#[new_array(derive_with_deps(Display, FromStr, Serialize, Deserialize))]
+#[repr(transparent)]
+pub struct Bytes<const N: usize>(pub [u8; N]);
+
+pub type PubKey = Bytes<33>;
+pub type FullPubKey = Bytes<65>;
+
+#[derive(NewArray)]
+#[new_array(derive(Default, PartialEq, Eq, AsRef, From, Debug, ZeroizeMethod))]
+#[new_array(derive_with_deps(Zeroize, ZeroizeOnDrop, ConstantTimeEq))]
+#[new_array(eq = "constant_time")]
+pub struct SecretKey(pub [u8; 32]);
+
+#[derive(NewArray)]
+#[new_array(derive(Default, PartialEq, Eq, Debug, Bitwise, Bits), methods)]
+pub struct Bloom(pub [u8; 256]);
+
+#[derive(NewArray)]
+#[new_array(derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Debug))]
+#[new_array(derive(LowerHex), uint = "big_endian", methods)]
+pub struct U512(pub [u8; 64]);
+
+#[derive(NewArray)]
+#[new_array(derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Debug))]
+#[new_array(uint = "little_endian")]
+pub struct U256Le(pub [u8; 32]);
+
+#[derive(NewArray)]
+#[new_array(derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Debug))]
+#[new_array(uint = "little_endian")]
+pub struct U24Le(pub [u8; 3]);
+
+#[derive(NewArray)]
+#[new_array(derive(Default, PartialEq, Eq, Debug, Bits))]
+#[new_array(bit_order = "lsb0")]
+pub struct Bitmap(pub [u8; 128]);
+
+#[derive(NewArray)]
+#[new_array(derive(Default, PartialEq, Eq, Debug))]
+#[new_array(derive_with_deps(Display, FromStr, Serialize, Deserialize))]
+#[new_array(encoding = "base58check")]
+pub struct Address(pub [u8; 21]);
+
+#[derive(NewArray)]
+#[new_array(derive(Default, PartialEq, Eq, Debug))]
+#[new_array(derive_with_deps(Display, FromStr, Serialize, Deserialize))]
+#[new_array(encoding = "bech32m", hrp = "demo")]
+pub struct Bech32Key(pub [u8; 32]);
+
+#[derive(NewArray)]
+#[new_array(derive(Default, PartialEq, Eq, Debug))]
+#[new_array(derive_with_deps(Display, FromStr, Serialize, Deserialize))]
+#[new_array(encoding = "base64")]
+pub struct Nonce(pub [u8; 24]);
+
+#[derive(NewArray)]
+#[new_array(derive(Default, PartialEq, Eq, Hash, AsRef, From, Into, TryFrom, Debug))]
+#[new_array(derive_with_deps(Display, FromStr, Serialize, Deserialize, Zeroize))]
+#[new_array(methods)]
+pub struct Key {
+    #[new_array(inner)]
+    bytes: [u8; 33],
+}
+
+#[derive(NewArray)]
+#[new_array(derive(Default, PartialEq, Eq, PartialOrd, Ord, Hash, AsRef, From, Debug))]
+#[new_array(derive_with_deps(Display, FromStr, Serialize, Deserialize))]
+#[new_array(methods)]
+pub struct Id<T>([u8; 40], core::marker::PhantomData<T>);
+
+// The flat accessors of nested arrays are only generated with `methods`.
+#[derive(NewArray)]
+#[new_array(derive(Default, PartialEq, Eq, PartialOrd, Ord, Hash, From, Into, Debug))]
+#[new_array(derive(AsRef, Index, IndexMut, IntoIterator), methods)]
+pub struct MerklePath([[u8; 32]; 4]);
@@ -1,4 +1,58 @@
-use new_array_ci_demo::ByteN;
+use std::{convert::TryFrom, fmt, marker::PhantomData, str::FromStr};
+
+use new_array::NewArray;
+use new_array_ci_demo::{
+    Address, Byte2, ByteN, ByteNTryFromError, Empty, MerklePath, PubKey, U24Le, U256Le, U512,
+};
+
+#[derive(NewArray)]
+#[new_array(derive(PartialEq, Debug), derive_with_deps(Display, FromStr))]
+struct Hex4([u8; 4]);
+
+#[derive(NewArray)]
+#[new_array(derive(PartialEq, Debug, FromRef))]
+#[repr(transparent)]
+struct Tagged<T>([u8; 4], PhantomData<T>);
+
+#[derive(NewArray)]
+#[new_array(derive(PartialEq, Debug), derive_with_deps(Display, FromStr))]
+#[new_array(encoding = "base58")]
+struct Base58Text([u8; 12]);
+
+#[derive(NewArray)]
+#[new_array(derive(PartialEq, Debug), derive_with_deps(Display, FromStr))]
+#[new_array(encoding = "base58")]
+struct Base58Zeros([u8; 6]);
+
+#[derive(NewArray)]
+#[new_array(derive(PartialEq, Debug), derive_with_deps(Display, FromStr))]
+#[new_array(encoding = "base64")]
+struct Base64Text<const N: usize>([u8; N]);
+
+#[derive(NewArray)]
+#[new_array(derive(PartialEq, Debug), derive_with_deps(Display, FromStr))]
+#[new_array(encoding = "bech32", hrp = "a")]
+struct Bech32Empty([u8; 0]);
+
+#[derive(NewArray)]
+#[new_array(derive(PartialEq, Debug), derive_with_deps(Display, FromStr))]
+#[new_array(encoding = "bech32", hrp = "abcdef")]
+struct Bech32Data([u8; 20]);
+
+#[derive(NewArray)]
+#[new_array(derive(PartialEq, Debug), derive_with_deps(Display, FromStr))]
+#[new_array(encoding = "bech32", hrp = "a{b}")]
+struct Bech32Braces([u8; 4]);
+
+#[derive(NewArray)]
+#[new_array(derive(PartialEq, Debug), derive_with_deps(Display, FromStr))]
+#[new_array(encoding = "bech32m", hrp = "a")]
+struct Bech32mEmpty([u8; 0]);
+
+#[derive(NewArray)]
+#[new_array(derive(PartialEq, Debug), derive_with_deps(Display, FromStr))]
+#[new_array(encoding = "bech32m", hrp = "abcdef")]
+struct Bech32mData([u8; 20]);
 
 fn main() {
     let v = ByteN::default();
@@ -9,4 +63,219 @@ fn main() {
     ]);
     println!("{:#?}", v);
     println!("{:?}", v2);
+    println!(
+        "{} {:?} {:#?}",
+        Byte2([0x12, 0x34]),
+        Empty::default(),
+        Empty::default()
+    );
+    check_uint_le();
+    check_uint_be();
+    check_uint_short();
+    check_encodings();
+    check_try_from();
+    check_from_ref();
+    check_flat_rows();
+}
+
+fn check_uint_le() {
+    let zero = U256Le::default();
+    let one = U256Le::from(1);
+    let max = U256Le([0xff; 32]);
+    assert_eq!(U256Le::from(0x0102).0[..3], [0x02, 0x01, 0x00]);
+    assert_eq!(max.overflowing_add(one), (zero, true));
+    assert_eq!(max.overflowing_add(zero), (max, false));
+    assert_eq!(zero.overflowing_sub(one), (max, true));
+    assert_eq!(max.overflowing_sub(max), (zero, false));
+    // 2^128 * 2^128 overflows, while only the high bytes of `rhs` are out of the bounds.
+    let half = one << 128;
+    assert_eq!(half.overflowing_mul(half), (zero, true));
+    assert_eq!(half.checked_mul(half), None);
+    assert_eq!((one << 127).overflowing_mul(U256Le::from(2)), (half, false));
+    assert_eq!(max.overflowing_mul(max), (one, true));
+    // `Mul` panics on overflow in debug mode.
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(|_| {}));
+    let result = std::panic::catch_unwind(|| half * half);
+    std::panic::set_hook(hook);
+    assert_eq!(result.is_err(), cfg!(debug_assertions));
+    assert_eq!(one.overflowing_shl(255), (one << 255, false));
+    assert_eq!(one.overflowing_shl(256), (one, true));
+    assert_eq!((one << 255) >> 255, one);
+    assert_eq!(one.overflowing_shr(257), (zero, true));
+    assert!(one < half && half < max);
+}
+
+fn check_uint_be() {
+    let zero = U512::default();
+    let one = U512::from(1);
+    let max = U512([0xff; 64]);
+    assert_eq!(U512::from(0x0102).0[61..], [0x00, 0x01, 0x02]);
+    assert_eq!(max.overflowing_add(one), (zero, true));
+    assert_eq!(zero.overflowing_sub(one), (max, true));
+    // (2^64 - 1)^2 = 2^128 - 2^65 + 1
+    let square = U512::from(u64::MAX) * U512::from(u64::MAX);
+    assert_eq!(square, (one << 128) - (one << 65) + one);
+    let half = one << 256;
+    assert_eq!(half.overflowing_mul(half), (zero, true));
+    assert_eq!(max.overflowing_mul(max), (one, true));
+    assert_eq!(one.overflowing_shl(512), (one, true));
+    assert_eq!((one << 511) >> 511, one);
+    assert_eq!((one << 8).0[62], 0x01);
+    assert!(one < half && half < max);
+}
+
+fn check_uint_short() {
+    // 65536 * 65536 overflows, while only the high byte of `rhs` is out of the bounds.
+    let v = U24Le([0, 0, 1]);
+    assert_eq!(v.overflowing_mul(v), (U24Le::default(), true));
+    assert_eq!(U24Le::try_from(0x01_0203), Ok(U24Le([3, 2, 1])));
+    assert!(U24Le::try_from(0x0100_0000).is_err());
+}
+
+fn check_encoding<T>(value: T, text: &str)
+where
+    T: fmt::Display + FromStr + PartialEq + fmt::Debug,
+    T::Err: fmt::Debug,
+{
+    assert_eq!(value.to_string(), text);
+    assert_eq!(text.parse::<T>().unwrap(), value);
+}
+
+fn check_encodings() {
+    check_encoding(Hex4([0xde, 0xad, 0xbe, 0xef]), "deadbeef");
+    let hex4 = Ok(Hex4([0xde, 0xad, 0xbe, 0xef]));
+    assert_eq!("0xdeadbeef".parse::<Hex4>(), hex4);
+    assert_eq!("0XDEADBEEF".parse::<Hex4>(), hex4);
+    assert_eq!(
+        "deadbee".parse::<Hex4>(),
+        Err(Hex4FromStrError::OddLength { length: 7 })
+    );
+    assert_eq!(
+        "0xdeadbeef00".parse::<Hex4>(),
+        Err(Hex4FromStrError::InvalidLength {
+            expected: 4,
+            actual: 5
+        })
+    );
+    assert_eq!(
+        "0xdead\u{e9}eef".parse::<Hex4>(),
+        Err(Hex4FromStrError::InvalidCharacter {
+            character: '\u{e9}',
+            index: 6
+        })
+    );
+    // The examples in the Base58 draft.
+    check_encoding(Base58Text(*b"Hello World!"), "2NEpo7TZRRrLZSi2U");
+    check_encoding(
+        Base58Zeros([0x00, 0x00, 0x28, 0x7f, 0xb4, 0xcd]),
+        "11233QC4",
+    );
+    // The address of the compressed public key of the private key 1.
+    let mut address = [0u8; 21];
+    address[1..].copy_from_slice(&[
+        0x75, 0x1e, 0x76, 0xe8, 0x19, 0x91, 0x96, 0xd4, 0x54, 0x94, 0x1c, 0x45, 0xd1, 0xb3, 0xa3,
+        0x23, 0xf1, 0x43, 0x3b, 0xd6,
+    ]);
+    check_encoding(Address(address), "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH");
+    assert!("1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMJ"
+        .parse::<Address>()
+        .is_err());
+    // The test vectors in RFC 4648.
+    check_encoding(Base64Text(*b"foob"), "Zm9vYg==");
+    check_encoding(Base64Text(*b"fooba"), "Zm9vYmE=");
+    check_encoding(Base64Text(*b"foobar"), "Zm9vYmFy");
+    // The valid checksums in BIP-173 and BIP-350.
+    let data = [
+        0x00, 0x44, 0x32, 0x14, 0xc7, 0x42, 0x54, 0xb6, 0x35, 0xcf, 0x84, 0x65, 0x3a, 0x56, 0xd7,
+        0xc6, 0x75, 0xbe, 0x77, 0xdf,
+    ];
+    check_encoding(Bech32Empty([]), "a12uel5l");
+    check_encoding(
+        Bech32Data(data),
+        "abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw",
+    );
+    assert_eq!("A12UEL5L".parse::<Bech32Empty>().unwrap(), Bech32Empty([]));
+    assert!("a1lqfn3a".parse::<Bech32Empty>().is_err());
+    // Braces in the human-readable part are not format placeholders.
+    check_encoding(Bech32Braces([1, 2, 3, 4]), "a{b}1qypqxpq0m5emh");
+    let data = [
+        0xff, 0xbb, 0xcd, 0xeb, 0x38, 0xbd, 0xab, 0x49, 0xca, 0x30, 0x7b, 0x9a, 0xc5, 0xa9, 0x28,
+        0x39, 0x8a, 0x41, 0x88, 0x20,
+    ];
+    check_encoding(Bech32mEmpty([]), "a1lqfn3a");
+    check_encoding(
+        Bech32mData(data),
+        "abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx",
+    );
+}
+
+fn check_try_from() {
+    let mut data = [0u8; 34];
+    for (i, v) in data.iter_mut().enumerate() {
+        *v = i as u8;
+    }
+    let mut expected = [0u8; 33];
+    expected.copy_from_slice(&data[..33]);
+    assert_eq!(ByteN::try_from(&data[..33]), Ok(ByteN(expected)));
+    assert_eq!(ByteN::try_from(&mut data[..33]), Ok(ByteN(expected)));
+    assert_eq!(ByteN::try_from(data[..33].to_vec()), Ok(ByteN(expected)));
+    assert_eq!(
+        ByteN::try_from(data[..33].to_vec().into_boxed_slice()),
+        Ok(ByteN(expected))
+    );
+    let short = ByteNTryFromError {
+        expected: 33,
+        actual: 32,
+    };
+    assert_eq!(ByteN::try_from(&data[..32]), Err(short));
+    assert_eq!(ByteN::try_from(&mut data[..32]), Err(short));
+    assert_eq!(ByteN::try_from(data.to_vec()).unwrap_err().actual, 34);
+    assert_eq!(
+        ByteN::try_from(Vec::new().into_boxed_slice())
+            .unwrap_err()
+            .actual,
+        0
+    );
+    assert_eq!(PubKey::try_from(&data[1..34]).unwrap().0[0], 1);
+    assert!(PubKey::try_from(&data[..]).is_err());
+}
+
+fn check_from_ref() {
+    let mut data = [0u8; 40];
+    for (i, v) in data.iter_mut().enumerate() {
+        *v = i as u8;
+    }
+    let key = PubKey::from_slice_ref(&data[3..36]).unwrap();
+    assert_eq!(key.0[0], 3);
+    assert_eq!(key.0[32], 35);
+    assert!(PubKey::from_slice_ref(&data[3..35]).is_none());
+    assert!(PubKey::from_slice_mut(&mut data[3..37]).is_none());
+    PubKey::from_slice_mut(&mut data[7..40]).unwrap().0[0] = 0xff;
+    assert_eq!(data[7], 0xff);
+    let mut array = [1u8, 2, 3, 4];
+    assert_eq!(Tagged::<u32>::from_ref(&array).0, [1, 2, 3, 4]);
+    let tagged: &Tagged<u32> = (&array).into();
+    assert_eq!(tagged.0, [1, 2, 3, 4]);
+    Tagged::<u32>::from_mut(&mut array).0[3] = 5;
+    assert_eq!(array, [1, 2, 3, 5]);
+    let tagged = Tagged::<String>::from_slice_ref(&data[36..]).unwrap();
+    assert_eq!(tagged.0, [36, 37, 38, 39]);
+    assert!(Tagged::<String>::from_slice_ref(&data[37..]).is_none());
+    Tagged::<String>::from_slice_mut(&mut data[..4]).unwrap().0[1] = 0xee;
+    assert_eq!(data[..4], [0, 0xee, 2, 3]);
+}
+
+fn check_flat_rows() {
+    let mut path = MerklePath::from([[0u8; 32], [1; 32], [2; 32], [3; 32]]);
+    let flat = path.as_flat_bytes();
+    assert_eq!(flat.len(), 128);
+    for (i, row) in flat.chunks(32).enumerate() {
+        assert_eq!(row, &[i as u8; 32][..]);
+    }
+    path.as_flat_bytes_mut()[32 * 2 + 5] = 0xff;
+    assert_eq!(path[2][5], 0xff);
+    assert_eq!(path[2][4], 2);
+    assert_eq!(path[1][5], 1);
+    assert_eq!(path[3][5], 3);
 }
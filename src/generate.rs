@@ -6,172 +6,2312 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use quote::quote;
+use quote::{quote, ToTokens};
 
-use crate::parse::{DerivedTrait, DerivedTraitWithDeps};
+use crate::parse::{
+    BitOrder, DerefTarget, DerivedTrait, DerivedTraitWithDeps, DisplayStyle, ElemType, Encoding,
+    Endianness, EqMode, NewArrayDef,
+};
 
-impl DerivedTrait {
-    pub(crate) fn implement(self, name: &syn::Ident, length: usize) -> proc_macro2::TokenStream {
-        let name_str = &name.to_string();
-        let length_lit = syn::LitInt::new(&format!("{}", length), proc_macro2::Span::call_site());
+impl ToTokens for ElemType {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let ident = match self {
+            Self::U8 => "u8",
+            Self::U16 => "u16",
+            Self::U32 => "u32",
+            Self::U64 => "u64",
+            Self::U128 => "u128",
+            Self::Usize => "usize",
+            Self::I8 => "i8",
+            Self::I16 => "i16",
+            Self::I32 => "i32",
+            Self::I64 => "i64",
+            Self::I128 => "i128",
+            Self::Isize => "isize",
+            Self::Bool => "bool",
+            Self::Char => "char",
+            Self::F32 => "f32",
+            Self::F64 => "f64",
+        };
+        syn::Ident::new(ident, proc_macro2::Span::call_site()).to_tokens(tokens);
+    }
+}
+
+impl ElemType {
+    fn zero(self) -> proc_macro2::TokenStream {
         match self {
-            Self::Default => quote!(
-                impl ::core::default::Default for #name {
+            Self::Bool => quote!(false),
+            Self::Char => quote!('\0'),
+            Self::F32 | Self::F64 => quote!(0.0),
+            _ => quote!(0),
+        }
+    }
+
+    // Formats for an element in the normal mode and in the alternate mode.
+    fn debug_formats(self) -> (&'static str, &'static str) {
+        match self {
+            Self::U8 | Self::I8 => ("{}", "{:#04x}"),
+            Self::U16 | Self::I16 => ("{}", "{:#06x}"),
+            Self::U32 | Self::I32 => ("{}", "{:#010x}"),
+            Self::U64 | Self::I64 => ("{}", "{:#018x}"),
+            Self::U128 | Self::I128 => ("{}", "{:#034x}"),
+            Self::Usize | Self::Isize => ("{}", "{:#x}"),
+            Self::Bool | Self::Char | Self::F32 | Self::F64 => ("{:?}", "{:?}"),
+        }
+    }
+}
+
+impl NewArrayDef {
+    // Construct the struct from an expression of the inner array.
+    // The extra fields are default-constructed.
+    fn construct(&self, inner: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        let name = &self.name;
+        let field = &self.field;
+        let skipped = self.skipped.iter().map(|(member, is_phantom)| {
+            if *is_phantom {
+                quote!(#member: ::core::marker::PhantomData)
+            } else {
+                quote!(#member: ::core::default::Default::default())
+            }
+        });
+        quote!(#name { #field: #inner, #( #skipped, )* })
+    }
+
+    // The type of the elements, which are rows for nested arrays.
+    fn elem_type(&self) -> proc_macro2::TokenStream {
+        let elem = self.elem;
+        match self.row {
+            Some(ref row) => quote!([#elem; #row]),
+            None => quote!(#elem),
+        }
+    }
+
+    fn elem_zero(&self) -> proc_macro2::TokenStream {
+        let zero = self.elem.zero();
+        match self.row {
+            Some(ref row) => quote!([#zero; #row]),
+            None => zero,
+        }
+    }
+
+    // Whether the inner array is `[u8; N]`.
+    fn is_bytes(&self) -> bool {
+        self.elem == ElemType::U8 && self.row.is_none()
+    }
+
+    // Whether the struct could be constructed in constant expressions.
+    fn is_const_constructible(&self) -> bool {
+        self.skipped.iter().all(|(_, is_phantom)| *is_phantom)
+    }
+
+    pub(crate) fn implement_methods(&self) -> proc_macro2::TokenStream {
+        let name = &self.name;
+        let field = &self.field;
+        let (impl_generics, ty_generics, where_clause) = self.generics.split_for_impl();
+        let elem = self.elem_type();
+        let length = &self.length;
+        let zero = self.elem_zero();
+        let new_inner = self.construct(quote!(inner));
+        let (impl_zero, constness) = if self.is_const_constructible() {
+            let new_zero = self.construct(quote!([#zero; #length]));
+            (
+                quote!(
+                    /// The value whose elements are all zeros.
+                    pub const ZERO: Self = #new_zero;
+                ),
+                quote!(const),
+            )
+        } else {
+            (quote!(), quote!())
+        };
+        let (as_slice, as_mut_slice) = if self.is_bytes() {
+            (quote!(as_bytes), quote!(as_bytes_mut))
+        } else {
+            (quote!(as_slice), quote!(as_mut_slice))
+        };
+        let impl_flat = if let Some(ref row) = self.row {
+            let flat_elem = self.elem;
+            let (as_flat, as_flat_mut) = if flat_elem == ElemType::U8 {
+                (quote!(as_flat_bytes), quote!(as_flat_bytes_mut))
+            } else {
+                (quote!(as_flat_slice), quote!(as_flat_mut_slice))
+            };
+            // Arrays have no padding, so the rows are contiguous.
+            quote!(
+                impl #impl_generics #name #ty_generics #where_clause {
+                    /// Returns the elements of all rows as one slice.
                     #[inline]
-                    fn default() -> Self {
-                        Self([0; #length_lit])
+                    pub fn #as_flat(&self) -> &[#flat_elem] {
+                        unsafe {
+                            ::core::slice::from_raw_parts(
+                                self.#field.as_ptr() as *const #flat_elem,
+                                (#length) * (#row),
+                            )
+                        }
                     }
-                }
-            ),
-            Self::PartialEq => quote!(
-                impl ::core::cmp::PartialEq for #name {
+
+                    /// Returns the elements of all rows as one mutable slice.
                     #[inline]
-                    fn eq(&self, other: &Self) -> bool {
-                        (&self.0[..]).eq(&other.0[..])
+                    pub fn #as_flat_mut(&mut self) -> &mut [#flat_elem] {
+                        unsafe {
+                            ::core::slice::from_raw_parts_mut(
+                                self.#field.as_mut_ptr() as *mut #flat_elem,
+                                (#length) * (#row),
+                            )
+                        }
                     }
                 }
-            ),
-            Self::Eq => quote!(
-                impl ::core::cmp::Eq for #name {}
-            ),
-            Self::PartialOrd => quote!(
-                impl ::core::cmp::PartialOrd for #name {
+            )
+        } else {
+            quote!()
+        };
+        let impl_to_vec = if cfg!(feature = "alloc") {
+            quote!(
+                const _: () = {
+                    extern crate alloc;
+                    impl #impl_generics #name #ty_generics #where_clause {
+                        /// Copies the inner array into a new `Vec`.
+                        #[inline]
+                        pub fn to_vec(&self) -> alloc::vec::Vec<#elem> {
+                            self.#field.to_vec()
+                        }
+                    }
+                };
+            )
+        } else {
+            quote!()
+        };
+        quote!(
+            impl #impl_generics #name #ty_generics #where_clause {
+                /// The length of the inner array.
+                pub const LEN: usize = #length;
+
+                #impl_zero
+
+                /// Creates a new value from the inner array.
+                #[inline]
+                pub #constness fn new(inner: [#elem; #length]) -> Self {
+                    #new_inner
+                }
+
+                /// Returns the inner array as a slice.
+                #[inline]
+                pub fn #as_slice(&self) -> &[#elem] {
+                    &self.#field[..]
+                }
+
+                /// Returns the inner array as a mutable slice.
+                #[inline]
+                pub fn #as_mut_slice(&mut self) -> &mut [#elem] {
+                    &mut self.#field[..]
+                }
+
+                /// Returns the inner array.
+                #[inline]
+                pub fn into_inner(self) -> [#elem; #length] {
+                    self.#field
+                }
+
+                /// Returns `true` if all elements are zeros.
+                #[inline]
+                pub fn is_zero(&self) -> bool {
+                    self.#field.iter().all(|v| *v == #zero)
+                }
+            }
+
+            #impl_flat
+
+            #impl_to_vec
+        )
+    }
+}
+
+impl NewArrayDef {
+    // The hidden module which holds the helper functions of the encoding.
+    fn helpers_mod(&self) -> syn::Ident {
+        quote::format_ident!("__new_array_{}", self.name)
+    }
+
+    // The helper functions are shared by all encoders and decoders of the struct.
+    pub(crate) fn implement_helpers(&self) -> proc_macro2::TokenStream {
+        let encoded = self.config.traits_with_deps.iter().any(|(dt, _)| {
+            matches!(
+                dt,
+                DerivedTraitWithDeps::Display
+                    | DerivedTraitWithDeps::FromStr
+                    | DerivedTraitWithDeps::Serialize
+                    | DerivedTraitWithDeps::Deserialize
+            )
+        });
+        if !encoded || !self.is_bytes() {
+            return quote!();
+        }
+        let helpers = match self.config.encoding() {
+            Encoding::Base58Check => sha256(),
+            Encoding::Bech32 | Encoding::Bech32m => bech32_polymod(),
+            Encoding::Hex | Encoding::Base58 | Encoding::Base64 => return quote!(),
+        };
+        let helpers_mod = self.helpers_mod();
+        quote!(
+            #[doc(hidden)]
+            #[allow(non_snake_case)]
+            mod #helpers_mod {
+                #helpers
+            }
+        )
+    }
+
+    pub(crate) fn implement_uint(&self, endianness: Endianness) -> proc_macro2::TokenStream {
+        let name = &self.name;
+        let name_str = name.to_string();
+        let field = &self.field;
+        let (impl_generics, ty_generics, where_clause) = self.generics.split_for_impl();
+        let length = &self.length;
+        let new_res = self.construct(quote!(res));
+        let new_lhs = self.construct(quote!(self.#field));
+        // All calculations are done in little-endian order.
+        let swap = |arrays: &[&str]| {
+            if endianness == Endianness::Big {
+                let arrays = arrays.iter().map(|a| quote::format_ident!("{}", a));
+                quote!( #( #arrays.reverse(); )* )
+            } else {
+                quote!()
+            }
+        };
+        let swap_lhs_rhs = swap(&["lhs", "rhs"]);
+        let swap_lhs = swap(&["lhs"]);
+        let swap_res = swap(&["res"]);
+        let arith = [
+            ("add", "Add", "AddAssign", "add_assign", "+"),
+            ("sub", "Sub", "SubAssign", "sub_assign", "-"),
+            ("mul", "Mul", "MulAssign", "mul_assign", "*"),
+        ];
+        let shift = [
+            ("shl", "Shl", "ShlAssign", "shl_assign", "<<"),
+            ("shr", "Shr", "ShrAssign", "shr_assign", ">>"),
+        ];
+        let impl_variants = arith
+            .iter()
+            .map(|x| (x, quote!(Self)))
+            .chain(shift.iter().map(|x| (x, quote!(u32))))
+            .map(|((func, op, op_assign, func_assign, sym), rhs_ty)| {
+                let overflowing = quote::format_ident!("overflowing_{}", func);
+                let checked = quote::format_ident!("checked_{}", func);
+                let wrapping = quote::format_ident!("wrapping_{}", func);
+                let func = quote::format_ident!("{}", func);
+                let op = quote::format_ident!("{}", op);
+                let op_assign = quote::format_ident!("{}", op_assign);
+                let func_assign = quote::format_ident!("{}", func_assign);
+                let checked_doc =
+                    format!("Checked `{}`, returns `None` if overflow occurred.", sym);
+                let wrapping_doc = format!("Wrapping `{}`.", sym);
+                let panic_msg = format!("attempt to {} with overflow", func);
+                quote!(
+                    impl #impl_generics #name #ty_generics #where_clause {
+                        #[doc = #checked_doc]
+                        #[inline]
+                        pub fn #checked(self, rhs: #rhs_ty) -> ::core::option::Option<Self> {
+                            let (res, overflow) = self.#overflowing(rhs);
+                            if overflow {
+                                None
+                            } else {
+                                Some(res)
+                            }
+                        }
+
+                        #[doc = #wrapping_doc]
+                        #[inline]
+                        pub fn #wrapping(self, rhs: #rhs_ty) -> Self {
+                            self.#overflowing(rhs).0
+                        }
+                    }
+
+                    impl #impl_generics ::core::ops::#op<#rhs_ty> for #name #ty_generics
+                    #where_clause
+                    {
+                        type Output = Self;
+                        #[inline]
+                        fn #func(self, rhs: #rhs_ty) -> Self::Output {
+                            let (res, overflow) = self.#overflowing(rhs);
+                            if cfg!(debug_assertions) && overflow {
+                                panic!(#panic_msg);
+                            }
+                            res
+                        }
+                    }
+
+                    impl #impl_generics ::core::ops::#op_assign<#rhs_ty> for #name #ty_generics
+                    #where_clause
+                    {
+                        #[inline]
+                        fn #func_assign(&mut self, rhs: #rhs_ty) {
+                            let lhs: Self = #new_lhs;
+                            self.#field = ::core::ops::#op::#func(lhs, rhs).#field;
+                        }
+                    }
+                )
+            });
+        let too_short = format!(
+            "`{}` should have at least 8 bytes to hold a `u64`",
+            name_str
+        );
+        // A length literal which is too short for `u64` only gets a fallible conversion.
+        let short_length = match self.length {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Int(ref lit),
+                ..
+            }) => lit.base10_parse::<usize>().ok().filter(|len| *len < 8),
+            _ => None,
+        };
+        let impl_from_u64 = if short_length.is_some() {
+            quote!(
+                impl #impl_generics ::core::convert::TryFrom<u64> for #name #ty_generics
+                #where_clause
+                {
+                    type Error = ::core::num::TryFromIntError;
+                    /// Converts an unsigned integer, fails if the value is too large.
                     #[inline]
-                    fn partial_cmp(&self, other: &Self) -> Option<::core::cmp::Ordering> {
-                        (&self.0[..]).partial_cmp(&other.0[..])
+                    fn try_from(value: u64) -> ::core::result::Result<Self, Self::Error> {
+                        let bytes = value.to_le_bytes();
+                        if bytes[#length..].iter().any(|v| *v != 0) {
+                            // The same error as converting into a too small primitive integer.
+                            return Err(
+                                <u8 as ::core::convert::TryFrom<u16>>::try_from(u16::MAX)
+                                    .unwrap_err(),
+                            );
+                        }
+                        let mut res = [0u8; #length];
+                        res.copy_from_slice(&bytes[..#length]);
+                        #swap_res
+                        Ok(#new_res)
                     }
                 }
-            ),
-            Self::Ord => quote!(
-                impl ::core::cmp::Ord for #name {
+            )
+        } else {
+            quote!(
+                impl #impl_generics #name #ty_generics #where_clause {
+                    // For generic lengths, it's checked when `From<u64>` is used.
+                    const __FITS_U64: () = assert!((#length) >= 8, #too_short);
+                }
+
+                impl #impl_generics ::core::convert::From<u64> for #name #ty_generics
+                #where_clause
+                {
+                    /// Converts an unsigned integer.
                     #[inline]
-                    fn cmp(&self, other: &Self) -> ::core::cmp::Ordering {
-                        (&self.0[..]).cmp(&other.0[..])
+                    fn from(value: u64) -> Self {
+                        let () = Self::__FITS_U64;
+                        let mut res = [0u8; #length];
+                        res[..8].copy_from_slice(&value.to_le_bytes());
+                        #swap_res
+                        #new_res
+                    }
+                }
+            )
+        };
+        quote!(
+            impl #impl_generics #name #ty_generics #where_clause {
+                /// Calculates `self + rhs`, returns the wrapped result and whether an overflow
+                /// occurred.
+                pub fn overflowing_add(self, rhs: Self) -> (Self, bool) {
+                    let mut lhs = self.#field;
+                    let mut rhs = rhs.#field;
+                    #swap_lhs_rhs
+                    let mut res = [0u8; #length];
+                    let mut carry = 0u16;
+                    for (r, (a, b)) in res.iter_mut().zip(lhs.iter().zip(rhs.iter())) {
+                        let sum = u16::from(*a) + u16::from(*b) + carry;
+                        *r = sum as u8;
+                        carry = sum >> 8;
+                    }
+                    #swap_res
+                    (#new_res, carry != 0)
+                }
+
+                /// Calculates `self - rhs`, returns the wrapped result and whether an overflow
+                /// occurred.
+                pub fn overflowing_sub(self, rhs: Self) -> (Self, bool) {
+                    let mut lhs = self.#field;
+                    let mut rhs = rhs.#field;
+                    #swap_lhs_rhs
+                    let mut res = [0u8; #length];
+                    let mut borrow = false;
+                    for (r, (a, b)) in res.iter_mut().zip(lhs.iter().zip(rhs.iter())) {
+                        let (diff, o1) = a.overflowing_sub(*b);
+                        let (diff, o2) = diff.overflowing_sub(u8::from(borrow));
+                        *r = diff;
+                        borrow = o1 || o2;
+                    }
+                    #swap_res
+                    (#new_res, borrow)
+                }
+
+                /// Calculates `self * rhs`, returns the wrapped result and whether an overflow
+                /// occurred.
+                pub fn overflowing_mul(self, rhs: Self) -> (Self, bool) {
+                    let mut lhs = self.#field;
+                    let mut rhs = rhs.#field;
+                    #swap_lhs_rhs
+                    let len = lhs.len();
+                    let mut res = [0u8; #length];
+                    let mut overflow = false;
+                    for i in 0..len {
+                        if lhs[i] == 0 {
+                            continue;
+                        }
+                        let mut carry = 0u16;
+                        for j in 0..len {
+                            if i + j >= len {
+                                // The rest bytes of `rhs` are multiplied out of the bounds.
+                                if carry != 0 || rhs[j..].iter().any(|v| *v != 0) {
+                                    overflow = true;
+                                }
+                                carry = 0;
+                                break;
+                            }
+                            let prod = u16::from(lhs[i]) * u16::from(rhs[j])
+                                + u16::from(res[i + j])
+                                + carry;
+                            res[i + j] = prod as u8;
+                            carry = prod >> 8;
+                        }
+                        if carry != 0 {
+                            overflow = true;
+                        }
+                    }
+                    #swap_res
+                    (#new_res, overflow)
+                }
+
+                /// Shifts `self` left by `rhs` bits, returns the result and whether `rhs` is not
+                /// less than the bits of `self`.
+                ///
+                /// When `rhs` is too large, it's masked by the bits of `self` before shifting.
+                pub fn overflowing_shl(self, rhs: u32) -> (Self, bool) {
+                    let mut lhs = self.#field;
+                    #swap_lhs
+                    let len = lhs.len();
+                    let bits = len * 8;
+                    let overflow = rhs as usize >= bits;
+                    let shift = if bits == 0 { 0 } else { rhs as usize % bits };
+                    let (bytes, bits) = (shift / 8, (shift % 8) as u32);
+                    let mut res = [0u8; #length];
+                    for i in bytes..len {
+                        res[i] = lhs[i - bytes] << bits;
+                        if bits != 0 && i > bytes {
+                            res[i] |= lhs[i - bytes - 1] >> (8 - bits);
+                        }
                     }
+                    #swap_res
+                    (#new_res, overflow)
                 }
+
+                /// Shifts `self` right by `rhs` bits, returns the result and whether `rhs` is not
+                /// less than the bits of `self`.
+                ///
+                /// When `rhs` is too large, it's masked by the bits of `self` before shifting.
+                pub fn overflowing_shr(self, rhs: u32) -> (Self, bool) {
+                    let mut lhs = self.#field;
+                    #swap_lhs
+                    let len = lhs.len();
+                    let bits = len * 8;
+                    let overflow = rhs as usize >= bits;
+                    let shift = if bits == 0 { 0 } else { rhs as usize % bits };
+                    let (bytes, bits) = (shift / 8, (shift % 8) as u32);
+                    let mut res = [0u8; #length];
+                    for i in 0..(len - bytes) {
+                        res[i] = lhs[i + bytes] >> bits;
+                        if bits != 0 && i + bytes + 1 < len {
+                            res[i] |= lhs[i + bytes + 1] << (8 - bits);
+                        }
+                    }
+                    #swap_res
+                    (#new_res, overflow)
+                }
+            }
+
+            #( #impl_variants )*
+
+            #impl_from_u64
+        )
+    }
+}
+
+impl DerivedTrait {
+    pub(crate) fn implement(self, def: &NewArrayDef) -> proc_macro2::TokenStream {
+        let name = &def.name;
+        let (impl_generics, ty_generics, where_clause) = def.generics.split_for_impl();
+        let name_str = &name.to_string();
+        let elem = def.elem_type();
+        let zero = def.elem_zero();
+        let length = &def.length;
+        let field = &def.field;
+        let new_inner = def.construct(quote!(inner));
+        // Let rustc report the unsatisfied supertraits, e.g. `PartialEq` for `Eq`, at the trait in
+        // the attribute. They could be implemented anywhere, so they are not checked when parsing.
+        let self_ty = {
+            let self_ty = quote!(#name #ty_generics);
+            match def.config.trait_span(self) {
+                Some(span) => respan(self_ty, span),
+                None => self_ty,
+            }
+        };
+        match self {
+            Self::Clone => {
+                let skipped = def
+                    .skipped
+                    .iter()
+                    .map(|(member, _)| quote!(#member: ::core::clone::Clone::clone(&self.#member)));
+                quote!(
+                    impl #impl_generics ::core::clone::Clone for #name #ty_generics #where_clause {
+                        #[inline]
+                        fn clone(&self) -> Self {
+                            #name {
+                                #field: self.#field,
+                                #( #skipped, )*
+                            }
+                        }
+                    }
+                )
+            }
+            Self::Copy => quote!(
+                impl #impl_generics ::core::marker::Copy for #self_ty #where_clause {}
+            ),
+            Self::Default => {
+                let new_zero = def.construct(quote!([#zero; #length]));
+                quote!(
+                    impl #impl_generics ::core::default::Default for #name #ty_generics
+                    #where_clause
+                    {
+                        #[inline]
+                        fn default() -> Self {
+                            #new_zero
+                        }
+                    }
+                )
+            }
+            Self::PartialEq => {
+                let body = match def.config.eq_mode() {
+                    EqMode::ShortCircuit => quote!((&self.#field[..]).eq(&other.#field[..])),
+                    EqMode::ConstantTime => quote!(
+                        let mut diff = 0u8;
+                        for (a, b) in self.#field.iter().zip(other.#field.iter()) {
+                            // Access the accumulator in volatile on each iteration, so the
+                            // compiler can't see its value and can't insert any early return.
+                            unsafe {
+                                let acc = ::core::ptr::read_volatile(&diff);
+                                ::core::ptr::write_volatile(&mut diff, acc | (a ^ b));
+                            }
+                        }
+                        diff == 0
+                    ),
+                };
+                quote!(
+                    impl #impl_generics ::core::cmp::PartialEq for #name #ty_generics #where_clause {
+                        #[inline]
+                        fn eq(&self, other: &Self) -> bool {
+                            #body
+                        }
+                    }
+                )
+            }
+            Self::Eq => quote!(
+                impl #impl_generics ::core::cmp::Eq for #self_ty #where_clause {}
             ),
+            Self::PartialOrd => {
+                let body = if def.config.uint == Some(Endianness::Little) {
+                    quote!(self.#field.iter().rev().partial_cmp(other.#field.iter().rev()))
+                } else {
+                    quote!((&self.#field[..]).partial_cmp(&other.#field[..]))
+                };
+                quote!(
+                    impl #impl_generics ::core::cmp::PartialOrd for #self_ty #where_clause {
+                        #[inline]
+                        fn partial_cmp(&self, other: &Self) -> Option<::core::cmp::Ordering> {
+                            #body
+                        }
+                    }
+                )
+            }
+            Self::Ord => {
+                // Compare from the most significant byte for little-endian unsigned integers.
+                let body = if def.config.uint == Some(Endianness::Little) {
+                    quote!(self.#field.iter().rev().cmp(other.#field.iter().rev()))
+                } else {
+                    quote!((&self.#field[..]).cmp(&other.#field[..]))
+                };
+                quote!(
+                    impl #impl_generics ::core::cmp::Ord for #self_ty #where_clause {
+                        #[inline]
+                        fn cmp(&self, other: &Self) -> ::core::cmp::Ordering {
+                            #body
+                        }
+                    }
+                )
+            }
             Self::Hash => quote!(
-                impl ::core::hash::Hash for #name {
+                impl #impl_generics ::core::hash::Hash for #name #ty_generics #where_clause {
                     #[inline]
-                    fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
-                        ::core::hash::Hash::hash(&self.0[..], state)
+                    fn hash<__H: ::core::hash::Hasher>(&self, state: &mut __H) {
+                        ::core::hash::Hash::hash(&self.#field[..], state)
                     }
                 }
             ),
             Self::AsRef => quote!(
-                impl ::core::convert::AsRef<[u8]> for #name {
+                impl #impl_generics ::core::convert::AsRef<[#elem]> for #name #ty_generics #where_clause {
                     #[inline]
-                    fn as_ref(&self) -> &[u8] {
-                        &self.0[..]
+                    fn as_ref(&self) -> &[#elem] {
+                        &self.#field[..]
                     }
                 }
             ),
             Self::AsMut => quote!(
-                impl ::core::convert::AsMut<[u8]> for #name {
+                impl #impl_generics ::core::convert::AsMut<[#elem]> for #name #ty_generics #where_clause {
                     #[inline]
-                    fn as_mut(&mut self) -> &mut [u8] {
-                        &mut self.0[..]
+                    fn as_mut(&mut self) -> &mut [#elem] {
+                        &mut self.#field[..]
                     }
                 }
             ),
             Self::From => quote!(
-                impl ::core::convert::From<[u8; #length_lit]> for #name {
+                impl #impl_generics ::core::convert::From<[#elem; #length]> for #name #ty_generics #where_clause {
                     #[inline]
-                    fn from(inner: [u8; #length_lit]) -> Self {
-                        Self(inner)
+                    fn from(inner: [#elem; #length]) -> Self {
+                        #new_inner
                     }
                 }
             ),
-            Self::Into => quote!(
-                impl ::core::convert::Into<[u8; #length_lit]> for #name {
-                    #[inline]
-                    fn into(self) -> [u8; #length_lit] {
-                        self.0
+            Self::Into => {
+                let ref_generics = ref_generics(def);
+                let (ref_impl_generics, _, _) = ref_generics.split_for_impl();
+                quote!(
+                    impl #impl_generics ::core::convert::From<#name #ty_generics> for [#elem; #length]
+                    #where_clause
+                    {
+                        #[inline]
+                        fn from(outer: #name #ty_generics) -> Self {
+                            outer.#field
+                        }
                     }
-                }
-            ),
+
+                    impl #ref_impl_generics ::core::convert::From<&'__a #name #ty_generics>
+                        for [#elem; #length]
+                    #where_clause
+                    {
+                        #[inline]
+                        fn from(outer: &'__a #name #ty_generics) -> Self {
+                            outer.#field
+                        }
+                    }
+                )
+            }
+            Self::TryFrom => {
+                let vis = &def.vis;
+                let error_name = quote::format_ident!("{}TryFromError", name);
+                let error_doc = format!(
+                    "The error returned when converting a slice into `{}` failed.",
+                    name_str
+                );
+                let impl_try_from = |from: proc_macro2::TokenStream| {
+                    quote!(
+                        impl #impl_generics ::core::convert::TryFrom<#from> for #name #ty_generics
+                        #where_clause
+                        {
+                            type Error = #error_name;
+                            #[inline]
+                            fn try_from(slice: #from) -> ::core::result::Result<Self, Self::Error> {
+                                if slice.len() != (#length) {
+                                    return Err(#error_name {
+                                        expected: #length,
+                                        actual: slice.len(),
+                                    });
+                                }
+                                let mut inner = [#zero; #length];
+                                inner.copy_from_slice(&slice[..]);
+                                Ok(#new_inner)
+                            }
+                        }
+                    )
+                };
+                let impl_try_from_slice = impl_try_from(quote!(&[#elem]));
+                let impl_try_from_slice_mut = impl_try_from(quote!(&mut [#elem]));
+                let impl_try_from_alloc = if cfg!(feature = "alloc") {
+                    let impl_try_from_vec = impl_try_from(quote!(alloc::vec::Vec<#elem>));
+                    let impl_try_from_boxed = impl_try_from(quote!(alloc::boxed::Box<[#elem]>));
+                    quote!(
+                        const _: () = {
+                            extern crate alloc;
+                            #impl_try_from_vec
+                            #impl_try_from_boxed
+                        };
+                    )
+                } else {
+                    quote!()
+                };
+                quote!(
+                    #[doc = #error_doc]
+                    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+                    #vis struct #error_name {
+                        /// The length of the array.
+                        pub expected: usize,
+                        /// The length of the input.
+                        pub actual: usize,
+                    }
+
+                    impl ::core::fmt::Display for #error_name {
+                        fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                            write!(
+                                f,
+                                "invalid length, expect {} elements but got {} elements",
+                                self.expected, self.actual
+                            )
+                        }
+                    }
+
+                    #impl_try_from_slice
+                    #impl_try_from_slice_mut
+                    #impl_try_from_alloc
+                )
+            }
             Self::Borrow => quote!(
-                impl ::core::borrow::Borrow<[u8]> for #name {
+                impl #impl_generics ::core::borrow::Borrow<[#elem]> for #name #ty_generics #where_clause {
                     #[inline]
-                    fn borrow(&self) -> &[u8] {
-                        &self.0[..]
+                    fn borrow(&self) -> &[#elem] {
+                        &self.#field[..]
                     }
                 }
             ),
             Self::BorrowMut => quote!(
-                impl ::core::borrow::BorrowMut<[u8]> for #name {
+                impl #impl_generics ::core::borrow::BorrowMut<[#elem]> for #self_ty #where_clause {
                     #[inline]
-                    fn borrow_mut(&mut self) -> &mut [u8] {
-                        &mut self.0[..]
+                    fn borrow_mut(&mut self) -> &mut [#elem] {
+                        &mut self.#field[..]
                     }
                 }
             ),
-            Self::Debug => quote!(
-                impl ::core::fmt::Debug for #name {
-                    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
-                        let s = &self.0[..];
-                        if  f.alternate() {
-                            write!(f, #name_str)?;
-                            write!(f, "([{:#04x}", s[0])?;
-                            for v in &s[1..] {
-                                write!(f, ", {:#04x}", v)?;
-                            }
+            Self::Debug => {
+                let (fmt, alt_fmt) = def.elem.debug_formats();
+                let (open, close) = match field {
+                    syn::Member::Named(ident) => (format!(" {{{{ {}: [", ident), "] }}"),
+                    syn::Member::Unnamed(_) => ("([".to_owned(), "])"),
+                };
+                let write_elems = |elems: proc_macro2::TokenStream| {
+                    quote!(for (i, v) in #elems.iter().enumerate() {
+                        if i != 0 {
+                            write!(f, ", ")?;
+                        }
+                        if f.alternate() {
+                            write!(f, #alt_fmt, v)?;
                         } else {
+                            write!(f, #fmt, v)?;
+                        }
+                    })
+                };
+                let write_elems = if def.row.is_some() {
+                    let write_row = write_elems(quote!(row));
+                    quote!(for (j, row) in self.#field.iter().enumerate() {
+                        if j != 0 {
+                            write!(f, ", ")?;
+                        }
+                        write!(f, "[")?;
+                        #write_row
+                        write!(f, "]")?;
+                    })
+                } else {
+                    write_elems(quote!(self.#field))
+                };
+                quote!(
+                    impl #impl_generics ::core::fmt::Debug for #name #ty_generics #where_clause {
+                        fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
                             write!(f, #name_str)?;
-                            write!(f, "([{}", s[0])?;
-                            for v in &s[1..] {
-                                write!(f, ", {}", v)?;
-                            }
+                            write!(f, #open)?;
+                            #write_elems
+                            write!(f, #close)
                         }
-                        write!(f, "])")
+                    }
+                )
+            }
+            Self::LowerHex => quote!(
+                impl #impl_generics ::core::fmt::LowerHex for #name #ty_generics #where_clause {
+                    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                        if f.alternate() {
+                            write!(f, "0x")?;
+                        }
+                        for v in &self.#field[..] {
+                            write!(f, "{:02x}", v)?;
+                        }
+                        Ok(())
                     }
                 }
             ),
-            Self::Drop => quote!(
-                impl ::core::ops::Drop for #name {
-                    #[inline]
-                    fn drop(&mut self) {
-                        for elem in self.0.iter_mut() {
-                            unsafe {
-                                ::core::ptr::write_volatile(elem, 0);
-                            }
-                            ::core::sync::atomic::compiler_fence(
-                                ::core::sync::atomic::Ordering::SeqCst);
+            Self::UpperHex => quote!(
+                impl #impl_generics ::core::fmt::UpperHex for #name #ty_generics #where_clause {
+                    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                        if f.alternate() {
+                            write!(f, "0x")?;
+                        }
+                        for v in &self.#field[..] {
+                            write!(f, "{:02X}", v)?;
                         }
+                        Ok(())
                     }
                 }
             ),
+            Self::Deref => {
+                let (target, body) = deref_target(def);
+                quote!(
+                    impl #impl_generics ::core::ops::Deref for #name #ty_generics #where_clause {
+                        type Target = #target;
+                        #[inline]
+                        fn deref(&self) -> &Self::Target {
+                            &self.#field #body
+                        }
+                    }
+                )
+            }
+            Self::DerefMut => {
+                let (_, body) = deref_target(def);
+                quote!(
+                    impl #impl_generics ::core::ops::DerefMut for #self_ty #where_clause {
+                        #[inline]
+                        fn deref_mut(&mut self) -> &mut Self::Target {
+                            &mut self.#field #body
+                        }
+                    }
+                )
+            }
+            Self::Index => {
+                let index_generics = index_generics(def);
+                let (index_impl_generics, _, _) = index_generics.split_for_impl();
+                quote!(
+                    impl #index_impl_generics ::core::ops::Index<__I> for #name #ty_generics
+                    #where_clause
+                    {
+                        type Output = __I::Output;
+                        #[inline]
+                        fn index(&self, index: __I) -> &Self::Output {
+                            ::core::ops::Index::index(&self.#field[..], index)
+                        }
+                    }
+                )
+            }
+            Self::IndexMut => {
+                let index_generics = index_generics(def);
+                let (index_impl_generics, _, _) = index_generics.split_for_impl();
+                quote!(
+                    impl #index_impl_generics ::core::ops::IndexMut<__I> for #self_ty
+                    #where_clause
+                    {
+                        #[inline]
+                        fn index_mut(&mut self, index: __I) -> &mut Self::Output {
+                            ::core::ops::IndexMut::index_mut(&mut self.#field[..], index)
+                        }
+                    }
+                )
+            }
+            Self::Bitwise => {
+                let ref_generics = ref_generics(def);
+                let (ref_impl_generics, _, _) = ref_generics.split_for_impl();
+                let ops = [
+                    (
+                        "BitAnd",
+                        "bitand",
+                        "BitAndAssign",
+                        "bitand_assign",
+                        quote!(&=),
+                    ),
+                    ("BitOr", "bitor", "BitOrAssign", "bitor_assign", quote!(|=)),
+                    (
+                        "BitXor",
+                        "bitxor",
+                        "BitXorAssign",
+                        "bitxor_assign",
+                        quote!(^=),
+                    ),
+                ];
+                let impl_ops = ops.iter().map(|(op, func, op_assign, func_assign, assign)| {
+                    let op = quote::format_ident!("{}", op);
+                    let func = quote::format_ident!("{}", func);
+                    let op_assign = quote::format_ident!("{}", op_assign);
+                    let func_assign = quote::format_ident!("{}", func_assign);
+                    quote!(
+                        impl #impl_generics ::core::ops::#op_assign for #name #ty_generics
+                        #where_clause
+                        {
+                            #[inline]
+                            fn #func_assign(&mut self, rhs: Self) {
+                                for (v, r) in self.#field.iter_mut().zip(rhs.#field.iter()) {
+                                    *v #assign *r;
+                                }
+                            }
+                        }
+
+                        impl #ref_impl_generics ::core::ops::#op_assign<&'__a #name #ty_generics>
+                            for #name #ty_generics
+                        #where_clause
+                        {
+                            #[inline]
+                            fn #func_assign(&mut self, rhs: &'__a #name #ty_generics) {
+                                for (v, r) in self.#field.iter_mut().zip(rhs.#field.iter()) {
+                                    *v #assign *r;
+                                }
+                            }
+                        }
+
+                        impl #impl_generics ::core::ops::#op for #name #ty_generics #where_clause {
+                            type Output = Self;
+                            #[inline]
+                            fn #func(mut self, rhs: Self) -> Self::Output {
+                                ::core::ops::#op_assign::#func_assign(&mut self, &rhs);
+                                self
+                            }
+                        }
+
+                        impl #ref_impl_generics ::core::ops::#op<&'__a #name #ty_generics>
+                            for #name #ty_generics
+                        #where_clause
+                        {
+                            type Output = Self;
+                            #[inline]
+                            fn #func(mut self, rhs: &'__a #name #ty_generics) -> Self::Output {
+                                ::core::ops::#op_assign::#func_assign(&mut self, rhs);
+                                self
+                            }
+                        }
+
+                        impl #ref_impl_generics ::core::ops::#op<&'__a #name #ty_generics>
+                            for &'__a #name #ty_generics
+                        #where_clause
+                        {
+                            type Output = #name #ty_generics;
+                            #[inline]
+                            fn #func(self, rhs: &'__a #name #ty_generics) -> Self::Output {
+                                let mut inner = self.#field;
+                                for (v, r) in inner.iter_mut().zip(rhs.#field.iter()) {
+                                    *v #assign *r;
+                                }
+                                #new_inner
+                            }
+                        }
+                    )
+                });
+                quote!(
+                    #( #impl_ops )*
+
+                    impl #impl_generics ::core::ops::Not for #name #ty_generics #where_clause {
+                        type Output = Self;
+                        #[inline]
+                        fn not(mut self) -> Self::Output {
+                            for v in self.#field.iter_mut() {
+                                *v = !*v;
+                            }
+                            self
+                        }
+                    }
+
+                    impl #ref_impl_generics ::core::ops::Not for &'__a #name #ty_generics
+                    #where_clause
+                    {
+                        type Output = #name #ty_generics;
+                        #[inline]
+                        fn not(self) -> Self::Output {
+                            let mut inner = self.#field;
+                            for v in inner.iter_mut() {
+                                *v = !*v;
+                            }
+                            #new_inner
+                        }
+                    }
+                )
+            }
+            Self::Drop => {
+                let wipe = wipe(def);
+                quote!(
+                    impl #impl_generics ::core::ops::Drop for #name #ty_generics #where_clause {
+                        #[inline]
+                        fn drop(&mut self) {
+                            #wipe
+                        }
+                    }
+                )
+            }
+            Self::IntoIterator => {
+                let ref_generics = ref_generics(def);
+                let (ref_impl_generics, _, _) = ref_generics.split_for_impl();
+                quote!(
+                    impl #impl_generics ::core::iter::IntoIterator for #name #ty_generics
+                    #where_clause
+                    {
+                        type Item = #elem;
+                        type IntoIter = ::core::array::IntoIter<#elem, #length>;
+                        #[inline]
+                        fn into_iter(self) -> Self::IntoIter {
+                            ::core::iter::IntoIterator::into_iter(self.#field)
+                        }
+                    }
+
+                    impl #ref_impl_generics ::core::iter::IntoIterator for &'__a #name #ty_generics
+                    #where_clause
+                    {
+                        type Item = &'__a #elem;
+                        type IntoIter = ::core::slice::Iter<'__a, #elem>;
+                        #[inline]
+                        fn into_iter(self) -> Self::IntoIter {
+                            self.#field.iter()
+                        }
+                    }
+
+                    impl #ref_impl_generics ::core::iter::IntoIterator for &'__a mut #name #ty_generics
+                    #where_clause
+                    {
+                        type Item = &'__a mut #elem;
+                        type IntoIter = ::core::slice::IterMut<'__a, #elem>;
+                        #[inline]
+                        fn into_iter(self) -> Self::IntoIter {
+                            self.#field.iter_mut()
+                        }
+                    }
+                )
+            }
+            Self::ZeroizeMethod => {
+                let wipe = wipe(def);
+                quote!(
+                    impl #impl_generics #name #ty_generics #where_clause {
+                        /// Overwrites the inner array with zeros.
+                        ///
+                        /// The write is volatile, so it won't be optimized away.
+                        #[inline]
+                        pub fn zeroize(&mut self) {
+                            #wipe
+                        }
+                    }
+                )
+            }
+            Self::FromRef => {
+                let ref_generics = ref_generics(def);
+                let (ref_impl_generics, _, _) = ref_generics.split_for_impl();
+                quote!(
+                    impl #ref_impl_generics ::core::convert::From<&'__a [#elem; #length]>
+                        for &'__a #name #ty_generics
+                    #where_clause
+                    {
+                        #[inline]
+                        fn from(inner: &'__a [#elem; #length]) -> Self {
+                            #name::from_ref(inner)
+                        }
+                    }
+
+                    impl #impl_generics #name #ty_generics #where_clause {
+                        /// Converts a reference of the inner array into a reference of this type,
+                        /// without copying.
+                        #[inline]
+                        pub fn from_ref(inner: &[#elem; #length]) -> &Self {
+                            // The struct is `#[repr(transparent)]`.
+                            unsafe { &*(inner as *const [#elem; #length] as *const Self) }
+                        }
+
+                        /// Converts a mutable reference of the inner array into a mutable reference of
+                        /// this type, without copying.
+                        #[inline]
+                        pub fn from_mut(inner: &mut [#elem; #length]) -> &mut Self {
+                            // The struct is `#[repr(transparent)]`.
+                            unsafe { &mut *(inner as *mut [#elem; #length] as *mut Self) }
+                        }
+
+                        /// Converts a slice into a reference of this type, without copying.
+                        ///
+                        /// Returns `None` if the length of the slice is not the length of the array.
+                        #[inline]
+                        pub fn from_slice_ref(slice: &[#elem]) -> ::core::option::Option<&Self> {
+                            if slice.len() == (#length) {
+                                // The length has been checked.
+                                Some(unsafe { &*(slice.as_ptr() as *const Self) })
+                            } else {
+                                None
+                            }
+                        }
+
+                        /// Converts a mutable slice into a mutable reference of this type, without
+                        /// copying.
+                        ///
+                        /// Returns `None` if the length of the slice is not the length of the array.
+                        #[inline]
+                        pub fn from_slice_mut(slice: &mut [#elem]) -> ::core::option::Option<&mut Self> {
+                            if slice.len() == (#length) {
+                                // The length has been checked.
+                                Some(unsafe { &mut *(slice.as_mut_ptr() as *mut Self) })
+                            } else {
+                                None
+                            }
+                        }
+                    }
+                )
+            }
+            Self::Bits => {
+                let (mask, leading, trailing, order_doc) = match def.config.bit_order() {
+                    BitOrder::Msb0 => (
+                        quote!(0x80 >> (index % 8)),
+                        quote!(self.#field.iter()),
+                        quote!(self.#field.iter().rev()),
+                        "The bit 0 is the most significant bit of the first byte.",
+                    ),
+                    BitOrder::Lsb0 => (
+                        quote!(1 << (index % 8)),
+                        quote!(self.#field.iter().rev()),
+                        quote!(self.#field.iter()),
+                        "The bit 0 is the least significant bit of the first byte.",
+                    ),
+                };
+                quote!(
+                    #[doc = #order_doc]
+                    impl #impl_generics #name #ty_generics #where_clause {
+                        /// Returns the bit at `index`.
+                        ///
+                        /// Panics if `index` is out of range.
+                        #[inline]
+                        pub fn get_bit(&self, index: usize) -> bool {
+                            self.#field[index / 8] & (#mask) != 0
+                        }
+
+                        /// Sets the bit at `index` to 1.
+                        ///
+                        /// Panics if `index` is out of range.
+                        #[inline]
+                        pub fn set_bit(&mut self, index: usize) {
+                            self.#field[index / 8] |= #mask;
+                        }
+
+                        /// Sets the bit at `index` to 0.
+                        ///
+                        /// Panics if `index` is out of range.
+                        #[inline]
+                        pub fn clear_bit(&mut self, index: usize) {
+                            self.#field[index / 8] &= !(#mask);
+                        }
+
+                        /// Flips the bit at `index`.
+                        ///
+                        /// Panics if `index` is out of range.
+                        #[inline]
+                        pub fn toggle_bit(&mut self, index: usize) {
+                            self.#field[index / 8] ^= #mask;
+                        }
+
+                        /// Returns the number of ones.
+                        #[inline]
+                        pub fn count_ones(&self) -> u32 {
+                            self.#field.iter().map(|v| v.count_ones()).sum()
+                        }
+
+                        /// Returns the number of zeros before the most significant one.
+                        ///
+                        /// The most significant bit is the bit 0 in `msb0` order, or the last bit
+                        /// in `lsb0` order.
+                        #[inline]
+                        pub fn leading_zeros(&self) -> u32 {
+                            let mut zeros = 0;
+                            for v in #leading {
+                                zeros += v.leading_zeros();
+                                if *v != 0 {
+                                    break;
+                                }
+                            }
+                            zeros
+                        }
+
+                        /// Returns the number of zeros after the least significant one.
+                        ///
+                        /// The least significant bit is the last bit in `msb0` order, or the bit 0
+                        /// in `lsb0` order.
+                        #[inline]
+                        pub fn trailing_zeros(&self) -> u32 {
+                            let mut zeros = 0;
+                            for v in #trailing {
+                                zeros += v.trailing_zeros();
+                                if *v != 0 {
+                                    break;
+                                }
+                            }
+                            zeros
+                        }
+
+                        /// Returns an iterator over the indexes of the ones, in ascending order.
+                        #[inline]
+                        pub fn iter_ones(&self) -> impl Iterator<Item = usize> + '_ {
+                            (0..(self.#field.len() * 8)).filter(move |index| self.get_bit(*index))
+                        }
+                    }
+                )
+            }
         }
     }
 }
 
 impl DerivedTraitWithDeps {
-    pub(crate) fn implement(self, name: &syn::Ident, length: usize) -> proc_macro2::TokenStream {
+    pub(crate) fn implement(self, def: &NewArrayDef) -> proc_macro2::TokenStream {
+        let name = &def.name;
+        let (impl_generics, ty_generics, where_clause) = def.generics.split_for_impl();
         let name_str = &name.to_string();
-        let length_lit = syn::LitInt::new(&format!("{}", length), proc_macro2::Span::call_site());
+        let length = &def.length;
+        let field = &def.field;
+        let new_inner = def.construct(quote!(inner));
         match self {
-            Self::Display => quote!(
-                impl ::core::fmt::Display for #name {
-                    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
-                        let s = &self.0[..];
-                        if  f.alternate() {
-                            write!(f, #name_str)?;
-                            write!(f, "([{:#04x}", s[0])?;
-                            for v in &s[1..] {
-                                write!(f, ", {:#04x}", v)?;
+            Self::Display => {
+                let encoding = def.config.encoding();
+                let write_hex = encoding.encode(def, &quote!(#length));
+                let body = match def.config.display_style() {
+                    DisplayStyle::ShortHex if encoding == Encoding::Hex => quote!(
+                        if s.len() > 4 {
+                            let t = &s[(s.len() - 2)..];
+                            write!(f, "{:02x}{:02x}\u{2026}{:02x}{:02x}", s[0], s[1], t[0], t[1])?;
+                        } else {
+                            #write_hex
+                        }
+                    ),
+                    DisplayStyle::PrefixedHex if encoding == Encoding::Hex => quote!(
+                        write!(f, "0x")?;
+                        #write_hex
+                    ),
+                    _ => write_hex,
+                };
+                quote!(
+                    impl #impl_generics ::core::fmt::Display for #name #ty_generics #where_clause {
+                        fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                            let s = &self.#field[..];
+                            #body
+                            Ok(())
+                        }
+                    }
+                )
+            }
+            Self::FromStr => {
+                let vis = &def.vis;
+                let encoding = def.config.encoding();
+                let error_name = quote::format_ident!("{}FromStrError", name);
+                let mut error_doc = format!(
+                    "The error returned when parsing a {} string into `{}` failed.",
+                    encoding.describe(),
+                    name_str
+                );
+                if encoding.requires_hrp() {
+                    error_doc.push_str(
+                        "\n\nThe strings longer than 90 characters are accepted, \
+                        so arrays of any length could be parsed.",
+                    );
+                }
+                let hrp = def.config.hrp.as_ref().map(|(hrp, _)| hrp.as_str());
+                let (variants, messages): (Vec<_>, Vec<_>) =
+                    encoding
+                        .failures()
+                        .iter()
+                        .map(|failure| {
+                            let (variant, doc, message) = match failure {
+                            DecodeFailure::InvalidCharacter => (
+                                quote!(InvalidCharacter { character: char, index: usize }),
+                                "An invalid character was found at the byte offset `index` of \
+                                the input."
+                                    .to_owned(),
+                                quote!(Self::InvalidCharacter { character, index } => write!(
+                                    f,
+                                    "invalid character {:?} at byte offset {}",
+                                    character, index
+                                )),
+                            ),
+                            DecodeFailure::OddLength => (
+                                quote!(OddLength { length: usize }),
+                                "The count of the hexadecimal digits is odd.".to_owned(),
+                                quote!(Self::OddLength { length } => write!(
+                                    f,
+                                    "odd count of hexadecimal digits ({})",
+                                    length
+                                )),
+                            ),
+                            DecodeFailure::InvalidLength => (
+                                quote!(InvalidLength { expected: usize, actual: usize }),
+                                "The count of the decoded bytes is not the length of the array."
+                                    .to_owned(),
+                                quote!(Self::InvalidLength { expected, actual } => write!(
+                                    f,
+                                    "invalid length, expect {} bytes but got {} bytes",
+                                    expected, actual
+                                )),
+                            ),
+                            DecodeFailure::MismatchedLength => (
+                                quote!(InvalidLength { expected: usize }),
+                                "The decoded bytes do not fit the length of the array.".to_owned(),
+                                quote!(Self::InvalidLength { expected } => write!(
+                                    f,
+                                    "invalid length, expect {} bytes",
+                                    expected
+                                )),
+                            ),
+                            DecodeFailure::InvalidChecksum => (
+                                quote!(InvalidChecksum),
+                                "The checksum does not match the data.".to_owned(),
+                                quote!(Self::InvalidChecksum => write!(f, "invalid checksum")),
+                            ),
+                            DecodeFailure::InvalidPrefix => (
+                                quote!(InvalidPrefix),
+                                format!(
+                                    "The human-readable part is not `{}`.",
+                                    hrp.unwrap_or_default()
+                                ),
+                                quote!(Self::InvalidPrefix => write!(
+                                    f,
+                                    "invalid human-readable part, expect {:?}",
+                                    #hrp
+                                )),
+                            ),
+                            DecodeFailure::InvalidPadding => (
+                                quote!(InvalidPadding),
+                                "The padding bits or characters are malformed.".to_owned(),
+                                quote!(Self::InvalidPadding => write!(f, "invalid padding")),
+                            ),
+                        };
+                            (quote!(#[doc = #doc] #variant), message)
+                        })
+                        .unzip();
+                let decode = encoding.decode(
+                    def,
+                    &DecodeErrors {
+                        invalid_character: quote!(#error_name::InvalidCharacter { character, index }),
+                        odd_length: quote!(#error_name::OddLength { length }),
+                        invalid_length: quote!(#error_name::InvalidLength { expected, actual }),
+                        mismatched_length: quote!(#error_name::InvalidLength { expected }),
+                        invalid_checksum: quote!(#error_name::InvalidChecksum),
+                        invalid_prefix: quote!(#error_name::InvalidPrefix),
+                        invalid_padding: quote!(#error_name::InvalidPadding),
+                    },
+                );
+                quote!(
+                    #[doc = #error_doc]
+                    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+                    #[allow(clippy::enum_variant_names)]
+                    #vis enum #error_name {
+                        #( #variants, )*
+                    }
+
+                    impl ::core::fmt::Display for #error_name {
+                        fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                            match *self {
+                                #( #messages, )*
+                            }
+                        }
+                    }
+
+                    impl #impl_generics ::core::str::FromStr for #name #ty_generics #where_clause {
+                        type Err = #error_name;
+                        fn from_str(s: &str) -> ::core::result::Result<Self, Self::Err> {
+                            let inner = #decode;
+                            Ok(#new_inner)
+                        }
+                    }
+                )
+            }
+            Self::Serialize => {
+                let human_readable = if def.is_bytes() {
+                    let encode = def.config.encoding().encode(def, &quote!(__N));
+                    quote!(if serializer.is_human_readable() {
+                        struct Encoded<'a, const __N: usize>(&'a [u8; __N]);
+                        impl<'a, const __N: usize> ::core::fmt::Display for Encoded<'a, __N> {
+                            fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                                let s = &self.0[..];
+                                #encode
+                                Ok(())
+                            }
+                        }
+                        return serializer.collect_str(&Encoded(&self.#field));
+                    })
+                } else {
+                    quote!()
+                };
+                quote!(
+                    impl #impl_generics ::serde::Serialize for #name #ty_generics #where_clause {
+                        fn serialize<__S>(
+                            &self,
+                            serializer: __S,
+                        ) -> ::core::result::Result<__S::Ok, __S::Error>
+                        where
+                            __S: ::serde::Serializer,
+                        {
+                            use ::serde::ser::SerializeTuple as _;
+                            #human_readable
+                            let mut tuple = serializer.serialize_tuple(#length)?;
+                            for v in self.#field.iter() {
+                                tuple.serialize_element(v)?;
+                            }
+                            tuple.end()
+                        }
+                    }
+                )
+            }
+            Self::Deserialize => {
+                let mut de_generics = def.generics.clone();
+                de_generics.params.insert(0, syn::parse_quote!('de));
+                let (de_impl_generics, _, _) = de_generics.split_for_impl();
+                let zero = def.elem_zero();
+                let encoding = def.config.encoding();
+                let expecting = if def.is_bytes() {
+                    let expecting =
+                        format!("{{}} bytes or a {} string of them", encoding.describe());
+                    quote!(write!(f, #expecting, #length))
+                } else {
+                    quote!(write!(f, "an array of length {}", #length))
+                };
+                let (visit_bytes, deserialize) = if def.is_bytes() {
+                    let hrp = def.config.hrp.as_ref().map(|(hrp, _)| hrp.as_str());
+                    let decode = encoding.decode(
+                        def,
+                        &DecodeErrors {
+                            invalid_character: quote!(__E::custom(format_args!(
+                                "invalid character {:?} at byte offset {}",
+                                character, index
+                            ))),
+                            odd_length: quote!(__E::custom(format_args!(
+                                "odd count of hexadecimal digits ({})",
+                                length
+                            ))),
+                            invalid_length: quote!(__E::invalid_length(actual, &self)),
+                            mismatched_length: quote!(__E::custom(format_args!(
+                                "invalid length, expect {} bytes",
+                                expected
+                            ))),
+                            invalid_checksum: quote!(__E::custom("invalid checksum")),
+                            invalid_prefix: quote!(__E::custom(format_args!(
+                                "invalid human-readable part, expect {:?}",
+                                #hrp
+                            ))),
+                            invalid_padding: quote!(__E::custom("invalid padding")),
+                        },
+                    );
+                    let visit_bytes = quote!(
+                        fn visit_str<__E>(
+                            self,
+                            s: &str,
+                        ) -> ::core::result::Result<Self::Value, __E>
+                        where
+                            __E: ::serde::de::Error,
+                        {
+                            let inner = #decode;
+                            Ok(#new_inner)
+                        }
+
+                        fn visit_bytes<__E>(
+                            self,
+                            v: &[u8],
+                        ) -> ::core::result::Result<Self::Value, __E>
+                        where
+                            __E: ::serde::de::Error,
+                        {
+                            if v.len() != (#length) {
+                                return Err(__E::invalid_length(v.len(), &self));
                             }
+                            let mut inner = [0; #length];
+                            inner.copy_from_slice(v);
+                            Ok(#new_inner)
+                        }
+                    );
+                    let deserialize = quote!(
+                        if deserializer.is_human_readable() {
+                            deserializer.deserialize_str(visitor)
                         } else {
-                            write!(f, #name_str)?;
-                            write!(f, "([{}", s[0])?;
-                            for v in &s[1..] {
-                                write!(f, ", {}", v)?;
+                            deserializer.deserialize_tuple(#length, visitor)
+                        }
+                    );
+                    (visit_bytes, deserialize)
+                } else {
+                    (
+                        quote!(),
+                        quote!(deserializer.deserialize_tuple(#length, visitor)),
+                    )
+                };
+                quote!(
+                    impl #de_impl_generics ::serde::Deserialize<'de> for #name #ty_generics
+                    #where_clause
+                    {
+                        fn deserialize<__D>(
+                            deserializer: __D,
+                        ) -> ::core::result::Result<Self, __D::Error>
+                        where
+                            __D: ::serde::Deserializer<'de>,
+                        {
+                            struct Visitor #impl_generics (
+                                ::core::marker::PhantomData<#name #ty_generics>
+                            ) #where_clause;
+
+                            impl #de_impl_generics ::serde::de::Visitor<'de> for Visitor #ty_generics
+                            #where_clause
+                            {
+                                type Value = #name #ty_generics;
+
+                                fn expecting(
+                                    &self,
+                                    f: &mut ::core::fmt::Formatter,
+                                ) -> ::core::fmt::Result {
+                                    #expecting
+                                }
+
+                                #visit_bytes
+
+                                fn visit_seq<__A>(
+                                    self,
+                                    mut seq: __A,
+                                ) -> ::core::result::Result<Self::Value, __A::Error>
+                                where
+                                    __A: ::serde::de::SeqAccess<'de>,
+                                {
+                                    let mut inner = [#zero; #length];
+                                    for (i, v) in inner.iter_mut().enumerate() {
+                                        *v = seq.next_element()?.ok_or_else(|| {
+                                            ::serde::de::Error::invalid_length(i, &self)
+                                        })?;
+                                    }
+                                    if seq.next_element::<::serde::de::IgnoredAny>()?.is_some() {
+                                        return Err(::serde::de::Error::invalid_length(
+                                            inner.len() + 1,
+                                            &self,
+                                        ));
+                                    }
+                                    Ok(#new_inner)
+                                }
                             }
+
+                            let visitor = Visitor(::core::marker::PhantomData);
+                            #deserialize
                         }
-                        write!(f, "])")
+                    }
+                )
+            }
+            Self::Zeroize => {
+                let wipe = wipe(def);
+                quote!(
+                    impl #impl_generics ::zeroize::Zeroize for #name #ty_generics #where_clause {
+                        #[inline]
+                        fn zeroize(&mut self) {
+                            #wipe
+                        }
+                    }
+                )
+            }
+            Self::ZeroizeOnDrop => {
+                let impl_drop = if def.config.has_trait(DerivedTrait::Drop) {
+                    quote!()
+                } else {
+                    DerivedTrait::Drop.implement(def)
+                };
+                quote!(
+                    #impl_drop
+                    impl #impl_generics ::zeroize::ZeroizeOnDrop for #name #ty_generics
+                    #where_clause
+                    {
+                    }
+                )
+            }
+            Self::ConstantTimeEq => quote!(
+                impl #impl_generics ::subtle::ConstantTimeEq for #name #ty_generics #where_clause {
+                    #[inline]
+                    fn ct_eq(&self, other: &Self) -> ::subtle::Choice {
+                        ::subtle::ConstantTimeEq::ct_eq(&self.#field[..], &other.#field[..])
                     }
                 }
             ),
-            _ => quote!(),
         }
     }
 }
+
+// The target type of `Deref`, and the index to get it from the inner array.
+fn deref_target(def: &NewArrayDef) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    let elem = def.elem_type();
+    let length = &def.length;
+    match def.config.deref_target() {
+        DerefTarget::Slice => (quote!([#elem]), quote!([..])),
+        DerefTarget::Array => (quote!([#elem; #length]), quote!()),
+    }
+}
+
+// The generics with an extra lifetime parameter `'__a` for references.
+fn ref_generics(def: &NewArrayDef) -> syn::Generics {
+    let mut generics = def.generics.clone();
+    generics.params.insert(0, syn::parse_quote!('__a));
+    generics
+}
+
+// The generics with an extra parameter `__I` for indexing the inner slice.
+fn index_generics(def: &NewArrayDef) -> syn::Generics {
+    let elem = def.elem_type();
+    let mut generics = def.generics.clone();
+    generics
+        .params
+        .push(syn::parse_quote!(__I: ::core::slice::SliceIndex<[#elem]>));
+    generics
+}
+
+// Overwrite the inner array with zeros in one volatile write.
+fn wipe(def: &NewArrayDef) -> proc_macro2::TokenStream {
+    let field = &def.field;
+    let length = &def.length;
+    let zero = def.elem_zero();
+    quote!(
+        unsafe {
+            ::core::ptr::write_volatile(&mut self.#field, [#zero; #length]);
+        }
+        ::core::sync::atomic::compiler_fence(::core::sync::atomic::Ordering::SeqCst);
+    )
+}
+
+// The ways to fail when decoding a string.
+#[derive(Clone, Copy)]
+enum DecodeFailure {
+    InvalidCharacter,
+    OddLength,
+    InvalidLength,
+    // An invalid length which could not be measured.
+    MismatchedLength,
+    InvalidChecksum,
+    InvalidPrefix,
+    InvalidPadding,
+}
+
+// Errors for decoding strings, as expressions of local variables.
+struct DecodeErrors {
+    // `character: char` and `index: usize`
+    invalid_character: proc_macro2::TokenStream,
+    // `length: usize`
+    odd_length: proc_macro2::TokenStream,
+    // `expected: usize` and `actual: usize`
+    invalid_length: proc_macro2::TokenStream,
+    // `expected: usize`
+    mismatched_length: proc_macro2::TokenStream,
+    invalid_checksum: proc_macro2::TokenStream,
+    invalid_prefix: proc_macro2::TokenStream,
+    invalid_padding: proc_macro2::TokenStream,
+}
+
+impl Encoding {
+    fn describe(self) -> &'static str {
+        match self {
+            Self::Hex => "hexadecimal",
+            Self::Base58 => "base58",
+            Self::Base58Check => "base58check",
+            Self::Base64 => "base64",
+            Self::Bech32 => "bech32",
+            Self::Bech32m => "bech32m",
+        }
+    }
+
+    fn failures(self) -> &'static [DecodeFailure] {
+        match self {
+            Self::Hex => &[
+                DecodeFailure::InvalidCharacter,
+                DecodeFailure::OddLength,
+                DecodeFailure::InvalidLength,
+            ],
+            Self::Base58 => &[
+                DecodeFailure::InvalidCharacter,
+                DecodeFailure::MismatchedLength,
+            ],
+            Self::Base58Check => &[
+                DecodeFailure::InvalidCharacter,
+                DecodeFailure::MismatchedLength,
+                DecodeFailure::InvalidChecksum,
+            ],
+            Self::Base64 => &[
+                DecodeFailure::InvalidCharacter,
+                DecodeFailure::InvalidPadding,
+                DecodeFailure::InvalidLength,
+            ],
+            Self::Bech32 | Self::Bech32m => &[
+                DecodeFailure::InvalidCharacter,
+                DecodeFailure::InvalidPrefix,
+                DecodeFailure::InvalidChecksum,
+                DecodeFailure::InvalidPadding,
+                DecodeFailure::InvalidLength,
+            ],
+        }
+    }
+
+    // The constant which the Bech32 checksum is XORed with.
+    fn bech32_constant(self) -> u32 {
+        if self == Self::Bech32m {
+            0x2bc8_30a3
+        } else {
+            1
+        }
+    }
+
+    // Write `s: &[u8]`, which has `length` bytes, into `f: &mut Formatter`.
+    fn encode(
+        self,
+        def: &NewArrayDef,
+        length: &proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        match self {
+            Self::Hex => quote!(for v in s {
+                write!(f, "{:02x}", v)?;
+            }),
+            Self::Base58 | Self::Base58Check => {
+                let (check_len, fill_check) = if self == Self::Base58Check {
+                    let helpers = def.helpers_mod();
+                    (
+                        quote!(4),
+                        quote!(
+                            let hash = #helpers::sha256(&#helpers::sha256(s));
+                            check.copy_from_slice(&hash[..4]);
+                        ),
+                    )
+                } else {
+                    (quote!(0), quote!())
+                };
+                quote!({
+                    const ALPHABET: &[u8; 58] =
+                        b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+                    // Treat the data and the checksum as one big-endian number.
+                    let mut number = [0u8; #length];
+                    number.copy_from_slice(s);
+                    let mut check = [0u8; #check_len];
+                    #fill_check
+                    let zeros = number
+                        .iter()
+                        .chain(check.iter())
+                        .take_while(|v| **v == 0)
+                        .count();
+                    // There are at most `N * 1.37 + 1` digits.
+                    let mut digits = ([[0u8; 2]; #length], [0u8; 8]);
+                    let mut count = 0;
+                    {
+                        let mut slots = digits
+                            .0
+                            .iter_mut()
+                            .flatten()
+                            .chain(digits.1.iter_mut())
+                            .rev();
+                        while number.iter().chain(check.iter()).any(|v| *v != 0) {
+                            let mut rem = 0u32;
+                            for v in number.iter_mut().chain(check.iter_mut()) {
+                                let acc = (rem << 8) | u32::from(*v);
+                                *v = (acc / 58) as u8;
+                                rem = acc % 58;
+                            }
+                            *slots.next().unwrap() = rem as u8;
+                            count += 1;
+                        }
+                    }
+                    for _ in 0..zeros {
+                        write!(f, "1")?;
+                    }
+                    let total = digits.0.len() * 2 + digits.1.len();
+                    for v in digits
+                        .0
+                        .iter()
+                        .flatten()
+                        .chain(digits.1.iter())
+                        .skip(total - count)
+                    {
+                        write!(f, "{}", char::from(ALPHABET[usize::from(*v)]))?;
+                    }
+                })
+            }
+            Self::Base64 => quote!({
+                const ALPHABET: &[u8; 64] =
+                    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+                for chunk in s.chunks(3) {
+                    let acc = chunk
+                        .iter()
+                        .chain([0u8; 2].iter())
+                        .take(3)
+                        .fold(0u32, |acc, v| (acc << 8) | u32::from(*v));
+                    for i in 0..4 {
+                        if i <= chunk.len() {
+                            let v = (acc >> (18 - 6 * i)) & 0x3f;
+                            write!(f, "{}", char::from(ALPHABET[v as usize]))?;
+                        } else {
+                            write!(f, "=")?;
+                        }
+                    }
+                }
+            }),
+            Self::Bech32 | Self::Bech32m => {
+                let hrp = def
+                    .config
+                    .hrp
+                    .as_ref()
+                    .map(|(hrp, _)| hrp.as_str())
+                    .unwrap_or_default();
+                let prefix = format!("{}1", hrp);
+                let hrp_checksum = bech32_hrp_checksum(hrp);
+                let constant = self.bech32_constant();
+                let helpers = def.helpers_mod();
+                quote!({
+                    const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+                    f.write_str(#prefix)?;
+                    let mut checksum: u32 = #hrp_checksum;
+                    let mut acc = 0u32;
+                    let mut bits = 0;
+                    for v in s {
+                        acc = (acc << 8) | u32::from(*v);
+                        bits += 8;
+                        while bits >= 5 {
+                            bits -= 5;
+                            let d = ((acc >> bits) & 0x1f) as u8;
+                            checksum = #helpers::polymod(checksum, d);
+                            write!(f, "{}", char::from(CHARSET[usize::from(d)]))?;
+                        }
+                        acc &= (1 << bits) - 1;
+                    }
+                    if bits > 0 {
+                        let d = ((acc << (5 - bits)) & 0x1f) as u8;
+                        checksum = #helpers::polymod(checksum, d);
+                        write!(f, "{}", char::from(CHARSET[usize::from(d)]))?;
+                    }
+                    for _ in 0..6 {
+                        checksum = #helpers::polymod(checksum, 0);
+                    }
+                    checksum ^= #constant;
+                    for i in 0..6 {
+                        let d = (checksum >> (5 * (5 - i))) & 0x1f;
+                        write!(f, "{}", char::from(CHARSET[d as usize]))?;
+                    }
+                })
+            }
+        }
+    }
+
+    // Decode `s: &str` into `[u8; N]`, returns `Err(_)` when failed.
+    fn decode(self, def: &NewArrayDef, errors: &DecodeErrors) -> proc_macro2::TokenStream {
+        let length = &def.length;
+        let DecodeErrors {
+            invalid_character,
+            odd_length,
+            invalid_length,
+            mismatched_length,
+            invalid_checksum,
+            invalid_prefix,
+            invalid_padding,
+        } = errors;
+        match self {
+            Self::Hex => quote!({
+                let input = s.as_bytes();
+                let offset = if input.starts_with(b"0x") || input.starts_with(b"0X") {
+                    2
+                } else {
+                    0
+                };
+                let digits = &input[offset..];
+                for (i, chr) in digits.iter().enumerate() {
+                    if !chr.is_ascii_hexdigit() {
+                        let index = offset + i;
+                        let character = s[index..].chars().next().unwrap();
+                        return Err(#invalid_character);
+                    }
+                }
+                if digits.len() % 2 != 0 {
+                    let length = digits.len();
+                    return Err(#odd_length);
+                }
+                if digits.len() / 2 != (#length) {
+                    let expected = #length;
+                    let actual = digits.len() / 2;
+                    return Err(#invalid_length);
+                }
+                let decode = |chr: u8| match chr {
+                    b'0'..=b'9' => chr - b'0',
+                    b'a'..=b'f' => chr - b'a' + 10,
+                    _ => chr - b'A' + 10,
+                };
+                let mut inner = [0; #length];
+                for (byte, pair) in inner.iter_mut().zip(digits.chunks(2)) {
+                    *byte = (decode(pair[0]) << 4) | decode(pair[1]);
+                }
+                inner
+            }),
+            Self::Base58 | Self::Base58Check => {
+                let (check_len, verify_check) = if self == Self::Base58Check {
+                    let helpers = def.helpers_mod();
+                    (
+                        quote!(4),
+                        quote!(
+                            let hash = #helpers::sha256(&#helpers::sha256(&inner));
+                            if hash[..4] != check[..] {
+                                return Err(#invalid_checksum);
+                            }
+                        ),
+                    )
+                } else {
+                    (quote!(0), quote!())
+                };
+                quote!({
+                    const ALPHABET: &[u8; 58] =
+                        b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+                    let input = s.as_bytes();
+                    for (index, chr) in input.iter().enumerate() {
+                        if !ALPHABET.contains(chr) {
+                            let character = s[index..].chars().next().unwrap();
+                            return Err(#invalid_character);
+                        }
+                    }
+                    // Treat the data and the checksum as one big-endian number.
+                    let mut inner = [0u8; #length];
+                    let mut check = [0u8; #check_len];
+                    for chr in input {
+                        let mut carry = ALPHABET.iter().position(|v| v == chr).unwrap() as u32;
+                        for v in inner.iter_mut().chain(check.iter_mut()).rev() {
+                            carry += u32::from(*v) * 58;
+                            *v = carry as u8;
+                            carry >>= 8;
+                        }
+                        if carry != 0 {
+                            let expected = #length;
+                            return Err(#mismatched_length);
+                        }
+                    }
+                    // Each leading "1" stands for a leading zero byte.
+                    let zeros = input.iter().take_while(|chr| **chr == b'1').count();
+                    let leading = inner
+                        .iter()
+                        .chain(check.iter())
+                        .take_while(|v| **v == 0)
+                        .count();
+                    if zeros != leading {
+                        let expected = #length;
+                        return Err(#mismatched_length);
+                    }
+                    #verify_check
+                    inner
+                })
+            }
+            Self::Base64 => quote!({
+                let input = s.as_bytes();
+                let digits = input
+                    .strip_suffix(b"==")
+                    .or_else(|| input.strip_suffix(b"="))
+                    .unwrap_or(input);
+                for (index, chr) in digits.iter().enumerate() {
+                    if !(chr.is_ascii_alphanumeric() || *chr == b'+' || *chr == b'/') {
+                        let character = s[index..].chars().next().unwrap();
+                        return Err(#invalid_character);
+                    }
+                }
+                if input.len() % 4 != 0 || digits.len() % 4 == 1 {
+                    return Err(#invalid_padding);
+                }
+                if digits.len() * 3 / 4 != (#length) {
+                    let expected = #length;
+                    let actual = digits.len() * 3 / 4;
+                    return Err(#invalid_length);
+                }
+                let decode = |chr: u8| match chr {
+                    b'A'..=b'Z' => chr - b'A',
+                    b'a'..=b'z' => chr - b'a' + 26,
+                    b'0'..=b'9' => chr - b'0' + 52,
+                    b'+' => 62,
+                    _ => 63,
+                };
+                let mut inner = [0u8; #length];
+                let mut bytes = inner.iter_mut();
+                let mut acc = 0u32;
+                let mut bits = 0;
+                for chr in digits {
+                    acc = (acc << 6) | u32::from(decode(*chr));
+                    bits += 6;
+                    if bits >= 8 {
+                        bits -= 8;
+                        *bytes.next().unwrap() = (acc >> bits) as u8;
+                        acc &= (1 << bits) - 1;
+                    }
+                }
+                if acc != 0 {
+                    return Err(#invalid_padding);
+                }
+                inner
+            }),
+            Self::Bech32 | Self::Bech32m => {
+                let hrp = def
+                    .config
+                    .hrp
+                    .as_ref()
+                    .map(|(hrp, _)| hrp.as_str())
+                    .unwrap_or_default();
+                let hrp_checksum = bech32_hrp_checksum(hrp);
+                let constant = self.bech32_constant();
+                let helpers = def.helpers_mod();
+                quote!({
+                    const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+                    // The limit of 90 characters is not checked, since the encoded arrays could
+                    // be longer than it.
+                    let input = s.as_bytes();
+                    // Mixed case strings are not allowed.
+                    let uppercase = matches!(
+                        input.iter().find(|chr| chr.is_ascii_alphabetic()),
+                        Some(chr) if chr.is_ascii_uppercase()
+                    );
+                    for (index, chr) in input.iter().enumerate() {
+                        if !(33..=126).contains(chr)
+                            || (chr.is_ascii_alphabetic() && chr.is_ascii_uppercase() != uppercase)
+                        {
+                            let character = s[index..].chars().next().unwrap();
+                            return Err(#invalid_character);
+                        }
+                    }
+                    let separator = match input.iter().rposition(|chr| *chr == b'1') {
+                        Some(separator) => separator,
+                        None => return Err(#invalid_prefix),
+                    };
+                    if !input[..separator].eq_ignore_ascii_case(#hrp.as_bytes()) {
+                        return Err(#invalid_prefix);
+                    }
+                    let data = &input[(separator + 1)..];
+                    let mut checksum: u32 = #hrp_checksum;
+                    for (i, chr) in data.iter().enumerate() {
+                        let lowercase = chr.to_ascii_lowercase();
+                        if let Some(d) = CHARSET.iter().position(|v| *v == lowercase) {
+                            checksum = #helpers::polymod(checksum, d as u8);
+                        } else {
+                            let index = separator + 1 + i;
+                            let character = char::from(*chr);
+                            return Err(#invalid_character);
+                        }
+                    }
+                    if data.len() < 6 || checksum != #constant {
+                        return Err(#invalid_checksum);
+                    }
+                    let payload = &data[..(data.len() - 6)];
+                    if payload.len() * 5 % 8 >= 5 {
+                        return Err(#invalid_padding);
+                    }
+                    if payload.len() * 5 / 8 != (#length) {
+                        let expected = #length;
+                        let actual = payload.len() * 5 / 8;
+                        return Err(#invalid_length);
+                    }
+                    let mut inner = [0u8; #length];
+                    let mut bytes = inner.iter_mut();
+                    let mut acc = 0u32;
+                    let mut bits = 0;
+                    for chr in payload {
+                        let lowercase = chr.to_ascii_lowercase();
+                        let d = CHARSET.iter().position(|v| *v == lowercase).unwrap() as u32;
+                        acc = (acc << 5) | d;
+                        bits += 5;
+                        if bits >= 8 {
+                            bits -= 8;
+                            *bytes.next().unwrap() = (acc >> bits) as u8;
+                            acc &= (1 << bits) - 1;
+                        }
+                    }
+                    if acc != 0 {
+                        return Err(#invalid_padding);
+                    }
+                    inner
+                })
+            }
+        }
+    }
+}
+
+fn respan(tokens: proc_macro2::TokenStream, span: proc_macro2::Span) -> proc_macro2::TokenStream {
+    tokens
+        .into_iter()
+        .map(|mut token| {
+            if let proc_macro2::TokenTree::Group(ref group) = token {
+                let stream = respan(group.stream(), span);
+                token = proc_macro2::Group::new(group.delimiter(), stream).into();
+            }
+            token.set_span(span);
+            token
+        })
+        .collect()
+}
+
+// The helper function `sha256(data: &[u8]) -> [u8; 32]`.
+fn sha256() -> proc_macro2::TokenStream {
+    quote!(
+        pub(super) fn sha256(data: &[u8]) -> [u8; 32] {
+            const K: [u32; 64] = [
+                0x428a_2f98,
+                0x7137_4491,
+                0xb5c0_fbcf,
+                0xe9b5_dba5,
+                0x3956_c25b,
+                0x59f1_11f1,
+                0x923f_82a4,
+                0xab1c_5ed5,
+                0xd807_aa98,
+                0x1283_5b01,
+                0x2431_85be,
+                0x550c_7dc3,
+                0x72be_5d74,
+                0x80de_b1fe,
+                0x9bdc_06a7,
+                0xc19b_f174,
+                0xe49b_69c1,
+                0xefbe_4786,
+                0x0fc1_9dc6,
+                0x240c_a1cc,
+                0x2de9_2c6f,
+                0x4a74_84aa,
+                0x5cb0_a9dc,
+                0x76f9_88da,
+                0x983e_5152,
+                0xa831_c66d,
+                0xb003_27c8,
+                0xbf59_7fc7,
+                0xc6e0_0bf3,
+                0xd5a7_9147,
+                0x06ca_6351,
+                0x1429_2967,
+                0x27b7_0a85,
+                0x2e1b_2138,
+                0x4d2c_6dfc,
+                0x5338_0d13,
+                0x650a_7354,
+                0x766a_0abb,
+                0x81c2_c92e,
+                0x9272_2c85,
+                0xa2bf_e8a1,
+                0xa81a_664b,
+                0xc24b_8b70,
+                0xc76c_51a3,
+                0xd192_e819,
+                0xd699_0624,
+                0xf40e_3585,
+                0x106a_a070,
+                0x19a4_c116,
+                0x1e37_6c08,
+                0x2748_774c,
+                0x34b0_bcb5,
+                0x391c_0cb3,
+                0x4ed8_aa4a,
+                0x5b9c_ca4f,
+                0x682e_6ff3,
+                0x748f_82ee,
+                0x78a5_636f,
+                0x84c8_7814,
+                0x8cc7_0208,
+                0x90be_fffa,
+                0xa450_6ceb,
+                0xbef9_a3f7,
+                0xc671_78f2,
+            ];
+            fn compress(state: &mut [u32; 8], block: &[u8]) {
+                let mut w = [0u32; 64];
+                for (i, word) in block.chunks(4).enumerate() {
+                    w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+                }
+                for i in 16..64 {
+                    let s0 =
+                        w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+                    let s1 =
+                        w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+                    w[i] = w[i - 16]
+                        .wrapping_add(s0)
+                        .wrapping_add(w[i - 7])
+                        .wrapping_add(s1);
+                }
+                let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+                for i in 0..64 {
+                    let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+                    let ch = (e & f) ^ (!e & g);
+                    let t1 = h
+                        .wrapping_add(s1)
+                        .wrapping_add(ch)
+                        .wrapping_add(K[i])
+                        .wrapping_add(w[i]);
+                    let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+                    let maj = (a & b) ^ (a & c) ^ (b & c);
+                    let t2 = s0.wrapping_add(maj);
+                    h = g;
+                    g = f;
+                    f = e;
+                    e = d.wrapping_add(t1);
+                    d = c;
+                    c = b;
+                    b = a;
+                    a = t1.wrapping_add(t2);
+                }
+                for (s, v) in state.iter_mut().zip([a, b, c, d, e, f, g, h].iter()) {
+                    *s = s.wrapping_add(*v);
+                }
+            }
+            let mut state: [u32; 8] = [
+                0x6a09_e667,
+                0xbb67_ae85,
+                0x3c6e_f372,
+                0xa54f_f53a,
+                0x510e_527f,
+                0x9b05_688c,
+                0x1f83_d9ab,
+                0x5be0_cd19,
+            ];
+            let mut chunks = data.chunks_exact(64);
+            for chunk in &mut chunks {
+                compress(&mut state, chunk);
+            }
+            let rest = chunks.remainder();
+            let mut block = [0u8; 64];
+            block[..rest.len()].copy_from_slice(rest);
+            block[rest.len()] = 0x80;
+            if rest.len() >= 56 {
+                compress(&mut state, &block);
+                block = [0u8; 64];
+            }
+            block[56..].copy_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+            compress(&mut state, &block);
+            let mut hash = [0u8; 32];
+            for (bytes, v) in hash.chunks_mut(4).zip(state.iter()) {
+                bytes.copy_from_slice(&v.to_be_bytes());
+            }
+            hash
+        }
+    )
+}
+
+// The helper function `polymod(checksum: u32, value: u8) -> u32` for one step of the Bech32
+// checksum.
+fn bech32_polymod() -> proc_macro2::TokenStream {
+    quote!(
+        pub(super) fn polymod(checksum: u32, value: u8) -> u32 {
+            const GENERATOR: [u32; 5] = [
+                0x3b6a_57b2,
+                0x2650_8e6d,
+                0x1ea1_19fa,
+                0x3d42_33dd,
+                0x2a14_62b3,
+            ];
+            let top = checksum >> 25;
+            let mut checksum = ((checksum & 0x01ff_ffff) << 5) ^ u32::from(value);
+            for (i, g) in GENERATOR.iter().enumerate() {
+                if (top >> i) & 1 == 1 {
+                    checksum ^= g;
+                }
+            }
+            checksum
+        }
+    )
+}
+
+// The Bech32 checksum after the expanded human-readable part, computed in advance.
+fn bech32_hrp_checksum(hrp: &str) -> u32 {
+    const GENERATOR: [u32; 5] = [
+        0x3b6a_57b2,
+        0x2650_8e6d,
+        0x1ea1_19fa,
+        0x3d42_33dd,
+        0x2a14_62b3,
+    ];
+    let polymod = |checksum: u32, value: u8| {
+        let top = checksum >> 25;
+        let mut checksum = ((checksum & 0x01ff_ffff) << 5) ^ u32::from(value);
+        for (i, g) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= g;
+            }
+        }
+        checksum
+    };
+    let expanded = hrp
+        .bytes()
+        .map(|chr| chr >> 5)
+        .chain(Some(0))
+        .chain(hrp.bytes().map(|chr| chr & 0x1f));
+    expanded.fold(1, polymod)
+}
@@ -15,8 +15,7 @@ pub(crate) mod parse;
 
 use crate::parse::NewArrayDef;
 
-/// Implement the same traits as `[u8; N]` (`N<=32`) for a new type of a fixed-size array
-/// automatically.
+/// Implement the same traits as `[u8; N]` for a new type of a fixed-size array automatically.
 #[proc_macro_derive(NewArray, attributes(new_array))]
 pub fn derive_new_array(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let new_array = syn::parse_macro_input!(input as NewArrayDef);
@@ -25,15 +24,29 @@ pub fn derive_new_array(input: proc_macro::TokenStream) -> proc_macro::TokenStre
             .config
             .traits
             .iter()
-            .map(|t| t.implement(&new_array.name, new_array.length));
+            .map(|(t, _)| t.implement(&new_array));
         let impl_traits_with_deps = new_array
             .config
             .traits_with_deps
             .iter()
-            .map(|t| t.implement(&new_array.name, new_array.length));
+            .map(|(t, _)| t.implement(&new_array));
+        let impl_methods = if new_array.config.methods {
+            new_array.implement_methods()
+        } else {
+            quote!()
+        };
+        let impl_helpers = new_array.implement_helpers();
+        let impl_uint = if let Some(endianness) = new_array.config.uint {
+            new_array.implement_uint(endianness)
+        } else {
+            quote!()
+        };
         quote!(
             #( #impl_traits )*
             #( #impl_traits_with_deps )*
+            #impl_methods
+            #impl_uint
+            #impl_helpers
         )
     };
     expanded.into()
@@ -11,9 +11,37 @@ use syn::{parse::Result as ParseResult, spanned::Spanned as _, Error as SynError
 const ATTR_NAME: &str = "new_array";
 const ATTR_DERIVE: &str = "derive";
 const ATTR_DERIVE_WITH_DEPS: &str = "derive_with_deps";
+const ATTR_DISPLAY: &str = "display";
+const ATTR_EQ: &str = "eq";
+const ATTR_DEREF: &str = "deref";
+const ATTR_METHODS: &str = "methods";
+const ATTR_UINT: &str = "uint";
+const ATTR_BIT_ORDER: &str = "bit_order";
+const ATTR_ENCODING: &str = "encoding";
+const ATTR_HRP: &str = "hrp";
+const ATTR_INNER: &str = "inner";
+const ATTR_SKIP: &str = "skip";
+
+// The attributes inside `#[new_array(...)]`.
+const ATTRS: &[&str] = &[
+    ATTR_DERIVE,
+    ATTR_DERIVE_WITH_DEPS,
+    ATTR_DISPLAY,
+    ATTR_EQ,
+    ATTR_DEREF,
+    ATTR_METHODS,
+    ATTR_UINT,
+    ATTR_BIT_ORDER,
+    ATTR_ENCODING,
+    ATTR_HRP,
+];
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub(crate) enum DerivedTrait {
+    // ::core::clone
+    Clone,
+    // ::core::marker
+    Copy,
     // ::core::default
     Default,
     // ::core::cmp
@@ -28,177 +56,700 @@ pub(crate) enum DerivedTrait {
     AsMut,
     From,
     Into,
+    TryFrom,
     // ::core::borrow
     Borrow,
     BorrowMut,
     // ::core::fmt
     Debug,
+    LowerHex,
+    UpperHex,
     // ::core::ops
+    Deref,
+    DerefMut,
+    Index,
+    IndexMut,
+    Bitwise,
     Drop,
+    // ::core::iter
+    IntoIterator,
+    // inherent methods
+    ZeroizeMethod,
+    FromRef,
+    Bits,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub(crate) enum DerivedTraitWithDeps {
     Display,
     FromStr,
+    // ::serde
+    Serialize,
+    Deserialize,
+    // ::zeroize
+    Zeroize,
+    ZeroizeOnDrop,
+    // ::subtle
+    ConstantTimeEq,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum ElemType {
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    Usize,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    Isize,
+    Bool,
+    Char,
+    F32,
+    F64,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub(crate) enum DisplayStyle {
+    // "abcdef0123"
+    #[default]
+    Hex,
+    // "0xabcdef0123"
+    PrefixedHex,
+    // "abcd…0123"
+    ShortHex,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub(crate) enum EqMode {
+    // Return as soon as a different element is found.
+    #[default]
+    ShortCircuit,
+    // Always compare all elements, without any branch.
+    ConstantTime,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub(crate) enum DerefTarget {
+    // `[T]`
+    #[default]
+    Slice,
+    // `[T; N]`
+    Array,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Endianness {
+    // The most significant byte is the first one.
+    Big,
+    // The least significant byte is the first one.
+    Little,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub(crate) enum BitOrder {
+    // The bit 0 is the most significant bit of the first byte.
+    #[default]
+    Msb0,
+    // The bit 0 is the least significant bit of the first byte.
+    Lsb0,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub(crate) enum Encoding {
+    // Base16, in lowercase.
+    #[default]
+    Hex,
+    // The Bitcoin alphabet.
+    Base58,
+    // The Bitcoin alphabet, with a 4-byte checksum of double SHA-256.
+    Base58Check,
+    // The standard alphabet, with padding.
+    Base64,
+    // BIP-173, requires a human-readable part.
+    // The limit of 90 characters is dropped, so arrays of any length could be encoded.
+    Bech32,
+    // BIP-350, requires a human-readable part, without the limit of 90 characters either.
+    Bech32m,
 }
 
-//    IntoIterator,
 //    FixedSizeArray,
-// "IntoIterator" => Ok(Self::IntoIterator),
 // "FixedSizeArray" => Ok(Self::FixedSizeArray),
 
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FieldRole {
+    // The field which holds the array.
+    Inner,
+    // An extra field, which is ignored by the generated code.
+    Skip,
+}
+
 pub(crate) struct NewArrayDef {
+    pub(crate) vis: syn::Visibility,
     pub(crate) name: syn::Ident,
-    pub(crate) length: usize,
+    pub(crate) generics: syn::Generics,
+    // The field which holds the array.
+    pub(crate) field: syn::Member,
+    // The extra fields, and whether each of them is a `PhantomData`.
+    pub(crate) skipped: Vec<(syn::Member, bool)>,
+    pub(crate) elem: ElemType,
+    // The length of each row, if the array is nested, e.g. `[[u8; 32]; N]`.
+    pub(crate) row: Option<syn::Expr>,
+    pub(crate) length: syn::Expr,
     pub(crate) config: NewArrayConf,
 }
 
+#[derive(Default)]
 pub(crate) struct NewArrayConf {
-    pub(crate) traits: Vec<DerivedTrait>,
-    pub(crate) traits_with_deps: Vec<DerivedTraitWithDeps>,
+    pub(crate) traits: Vec<(DerivedTrait, proc_macro2::Span)>,
+    pub(crate) traits_with_deps: Vec<(DerivedTraitWithDeps, proc_macro2::Span)>,
+    pub(crate) display: Option<(DisplayStyle, proc_macro2::Span)>,
+    pub(crate) eq: Option<(EqMode, proc_macro2::Span)>,
+    pub(crate) deref: Option<(DerefTarget, proc_macro2::Span)>,
+    pub(crate) methods: bool,
+    pub(crate) uint: Option<Endianness>,
+    pub(crate) bit_order: Option<(BitOrder, proc_macro2::Span)>,
+    pub(crate) encoding: Option<(Encoding, proc_macro2::Span)>,
+    // The human-readable part of Bech32 strings.
+    pub(crate) hrp: Option<(String, proc_macro2::Span)>,
 }
 
 impl DerivedTrait {
+    const NAMES: &'static [(&'static str, Self)] = &[
+        ("Clone", Self::Clone),
+        ("Copy", Self::Copy),
+        ("Default", Self::Default),
+        ("PartialEq", Self::PartialEq),
+        ("PartialOrd", Self::PartialOrd),
+        ("Eq", Self::Eq),
+        ("Ord", Self::Ord),
+        ("Hash", Self::Hash),
+        ("AsRef", Self::AsRef),
+        ("AsMut", Self::AsMut),
+        ("From", Self::From),
+        ("Into", Self::Into),
+        ("TryFrom", Self::TryFrom),
+        ("Borrow", Self::Borrow),
+        ("BorrowMut", Self::BorrowMut),
+        ("Debug", Self::Debug),
+        ("LowerHex", Self::LowerHex),
+        ("UpperHex", Self::UpperHex),
+        ("Deref", Self::Deref),
+        ("DerefMut", Self::DerefMut),
+        ("Index", Self::Index),
+        ("IndexMut", Self::IndexMut),
+        ("Bitwise", Self::Bitwise),
+        ("Drop", Self::Drop),
+        ("IntoIterator", Self::IntoIterator),
+        ("FromRef", Self::FromRef),
+        ("Bits", Self::Bits),
+        ("ZeroizeMethod", Self::ZeroizeMethod),
+    ];
+
     pub(crate) fn parse_from_input(input: &str, span: proc_macro2::Span) -> ParseResult<Self> {
-        match input {
-            "Default" => Ok(Self::Default),
-            "PartialEq" => Ok(Self::PartialEq),
-            "PartialOrd" => Ok(Self::PartialOrd),
-            "Eq" => Ok(Self::Eq),
-            "Ord" => Ok(Self::Ord),
-            "Hash" => Ok(Self::Hash),
-            "AsRef" => Ok(Self::AsRef),
-            "AsMut" => Ok(Self::AsMut),
-            "From" => Ok(Self::From),
-            "Into" => Ok(Self::Into),
-            "Borrow" => Ok(Self::Borrow),
-            "BorrowMut" => Ok(Self::BorrowMut),
-            "Debug" => Ok(Self::Debug),
-            "Drop" => Ok(Self::Drop),
-            _ => Err(SynError::new(span, "this attribute was unknown")),
+        if let Some((_, dt)) = Self::NAMES.iter().find(|(name, _)| *name == input) {
+            Ok(*dt)
+        } else if DerivedTraitWithDeps::NAMES
+            .iter()
+            .any(|(name, _)| *name == input)
+        {
+            Err(SynError::new(
+                span,
+                format!("this trait should be in `{}(...)`", ATTR_DERIVE_WITH_DEPS),
+            ))
+        } else {
+            let names = Self::NAMES.iter().map(|(name, _)| *name);
+            let other_names = DerivedTraitWithDeps::NAMES.iter().map(|(name, _)| *name);
+            Err(unknown_trait_error(
+                span,
+                input,
+                names,
+                (ATTR_DERIVE_WITH_DEPS, other_names),
+            ))
         }
     }
+
+    pub(crate) fn name(self) -> &'static str {
+        Self::NAMES
+            .iter()
+            .find(|(_, dt)| *dt == self)
+            .map(|(name, _)| *name)
+            .unwrap()
+    }
+
+    // Floating-point numbers only have a partial equivalence relation.
+    pub(crate) fn requires_total_eq(self) -> bool {
+        matches!(self, Self::Eq | Self::Ord | Self::Hash)
+    }
+
+    pub(crate) fn requires_bytes(self) -> bool {
+        matches!(self, Self::LowerHex | Self::UpperHex | Self::Bits)
+    }
 }
 
 impl DerivedTraitWithDeps {
+    const NAMES: &'static [(&'static str, Self)] = &[
+        ("Display", Self::Display),
+        ("FromStr", Self::FromStr),
+        ("Serialize", Self::Serialize),
+        ("Deserialize", Self::Deserialize),
+        ("Zeroize", Self::Zeroize),
+        ("ZeroizeOnDrop", Self::ZeroizeOnDrop),
+        ("ConstantTimeEq", Self::ConstantTimeEq),
+    ];
+
+    pub(crate) fn parse_from_input(input: &str, span: proc_macro2::Span) -> ParseResult<Self> {
+        if let Some((_, dt)) = Self::NAMES.iter().find(|(name, _)| *name == input) {
+            Ok(*dt)
+        } else if DerivedTrait::NAMES.iter().any(|(name, _)| *name == input) {
+            Err(SynError::new(
+                span,
+                format!("this trait should be in `{}(...)`", ATTR_DERIVE),
+            ))
+        } else {
+            let names = Self::NAMES.iter().map(|(name, _)| *name);
+            let other_names = DerivedTrait::NAMES.iter().map(|(name, _)| *name);
+            Err(unknown_trait_error(
+                span,
+                input,
+                names,
+                (ATTR_DERIVE, other_names),
+            ))
+        }
+    }
+
+    pub(crate) fn requires_bytes(self) -> bool {
+        matches!(self, Self::Display | Self::FromStr)
+    }
+}
+
+impl ElemType {
+    pub(crate) fn parse_from_input(input: &str) -> Option<Self> {
+        match input {
+            "u8" => Some(Self::U8),
+            "u16" => Some(Self::U16),
+            "u32" => Some(Self::U32),
+            "u64" => Some(Self::U64),
+            "u128" => Some(Self::U128),
+            "usize" => Some(Self::Usize),
+            "i8" => Some(Self::I8),
+            "i16" => Some(Self::I16),
+            "i32" => Some(Self::I32),
+            "i64" => Some(Self::I64),
+            "i128" => Some(Self::I128),
+            "isize" => Some(Self::Isize),
+            "bool" => Some(Self::Bool),
+            "char" => Some(Self::Char),
+            "f32" => Some(Self::F32),
+            "f64" => Some(Self::F64),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn is_bitwise(self) -> bool {
+        !matches!(self, Self::Char | Self::F32 | Self::F64)
+    }
+
+    pub(crate) fn is_float(self) -> bool {
+        matches!(self, Self::F32 | Self::F64)
+    }
+}
+
+impl DisplayStyle {
+    pub(crate) fn parse_from_input(input: &str, span: proc_macro2::Span) -> ParseResult<Self> {
+        match input {
+            "hex" => Ok(Self::Hex),
+            "prefixed_hex" => Ok(Self::PrefixedHex),
+            "short_hex" => Ok(Self::ShortHex),
+            _ => {
+                let names = ["hex", "prefixed_hex", "short_hex"];
+                Err(unknown_error(
+                    span,
+                    "this display style was unknown",
+                    input,
+                    names.iter().copied(),
+                ))
+            }
+        }
+    }
+}
+
+impl EqMode {
+    pub(crate) fn parse_from_input(input: &str, span: proc_macro2::Span) -> ParseResult<Self> {
+        match input {
+            "short_circuit" => Ok(Self::ShortCircuit),
+            "constant_time" => Ok(Self::ConstantTime),
+            _ => {
+                let names = ["short_circuit", "constant_time"];
+                Err(unknown_error(
+                    span,
+                    "this comparison mode was unknown",
+                    input,
+                    names.iter().copied(),
+                ))
+            }
+        }
+    }
+}
+
+impl DerefTarget {
+    pub(crate) fn parse_from_input(input: &str, span: proc_macro2::Span) -> ParseResult<Self> {
+        match input {
+            "slice" => Ok(Self::Slice),
+            "array" => Ok(Self::Array),
+            _ => {
+                let names = ["slice", "array"];
+                Err(unknown_error(
+                    span,
+                    "this deref target was unknown",
+                    input,
+                    names.iter().copied(),
+                ))
+            }
+        }
+    }
+}
+
+impl Endianness {
+    pub(crate) fn parse_from_input(input: &str, span: proc_macro2::Span) -> ParseResult<Self> {
+        match input {
+            "big_endian" => Ok(Self::Big),
+            "little_endian" => Ok(Self::Little),
+            _ => {
+                let names = ["big_endian", "little_endian"];
+                Err(unknown_error(
+                    span,
+                    "this endianness was unknown",
+                    input,
+                    names.iter().copied(),
+                ))
+            }
+        }
+    }
+}
+
+impl BitOrder {
+    pub(crate) fn parse_from_input(input: &str, span: proc_macro2::Span) -> ParseResult<Self> {
+        match input {
+            "msb0" => Ok(Self::Msb0),
+            "lsb0" => Ok(Self::Lsb0),
+            _ => {
+                let names = ["msb0", "lsb0"];
+                Err(unknown_error(
+                    span,
+                    "this bit order was unknown",
+                    input,
+                    names.iter().copied(),
+                ))
+            }
+        }
+    }
+}
+
+impl Encoding {
     pub(crate) fn parse_from_input(input: &str, span: proc_macro2::Span) -> ParseResult<Self> {
         match input {
-            "Display" => Ok(Self::Display),
-            "FromStr" => Ok(Self::FromStr),
-            _ => Err(SynError::new(span, "this attribute was unknown")),
+            "hex" => Ok(Self::Hex),
+            "base58" => Ok(Self::Base58),
+            "base58check" => Ok(Self::Base58Check),
+            "base64" => Ok(Self::Base64),
+            "bech32" => Ok(Self::Bech32),
+            "bech32m" => Ok(Self::Bech32m),
+            _ => {
+                let names = [
+                    "hex",
+                    "base58",
+                    "base58check",
+                    "base64",
+                    "bech32",
+                    "bech32m",
+                ];
+                Err(unknown_error(
+                    span,
+                    "this encoding was unknown",
+                    input,
+                    names.iter().copied(),
+                ))
+            }
         }
     }
+
+    pub(crate) fn requires_hrp(self) -> bool {
+        matches!(self, Self::Bech32 | Self::Bech32m)
+    }
 }
 
 impl syn::parse::Parse for NewArrayDef {
     fn parse(input: syn::parse::ParseStream) -> ParseResult<Self> {
         let derive_input: syn::DeriveInput = input.parse()?;
-        let attrs_span = derive_input.span();
         let syn::DeriveInput {
-            attrs, ident, data, ..
+            attrs,
+            vis,
+            ident,
+            generics,
+            data,
         } = derive_input;
         let ident_span = ident.span();
-        match data {
+        let fields = match data {
             syn::Data::Struct(data) => match data.fields {
-                syn::Fields::Unnamed(unnamed_fields) => {
-                    let fields = unnamed_fields.unnamed.into_iter().collect::<Vec<_>>();
-                    if fields.len() != 1 {
-                        Err(SynError::new(ident_span, "should have only one field"))
-                    } else {
-                        let field = &fields[0];
-                        for attr in &field.attrs[..] {
-                            if let syn::AttrStyle::Outer = attr.style {
-                                let meta = attr.parse_meta().map_err(|_| {
-                                    SynError::new(attr.span(), "failed to parse the attributes")
-                                })?;
-                                if meta.path().is_ident(ATTR_NAME) {
-                                    return Err(SynError::new(
-                                        meta.span(),
-                                        "should not be a field attribute",
-                                    ));
-                                }
-                            }
-                        }
-                        match field.ty {
-                            syn::Type::Array(ref ta) => {
-                                match ta.elem.as_ref() {
-                                    syn::Type::Path(ref tp) => {
-                                        if tp.qself.is_none() && tp.path.is_ident("u8") {
-                                            Ok(())
-                                        } else {
-                                            Err(SynError::new(
-                                                tp.path.span(),
-                                                "the type should be `u8`",
-                                            ))
-                                        }
-                                    }
-                                    _ => Err(SynError::new(
-                                        ta.elem.span(),
-                                        "the type should be `u8`",
-                                    )),
-                                }?;
-                                match ta.len {
-                                    syn::Expr::Lit(ref el) => match el.lit {
-                                        syn::Lit::Int(ref li) => {
-                                            let length = li.base10_parse::<usize>()?;
-                                            if length <= 32 {
-                                                Err(SynError::new(
-                                                    li.span(),
-                                                    "the length is smaller than or equal to 32, \
-                                                    don't have to derive `NewArray`",
-                                                ))
-                                            } else {
-                                                let config = parse_attrs(attrs_span, &attrs[..])?;
-                                                Ok(Self {
-                                                    name: ident,
-                                                    length,
-                                                    config,
-                                                })
-                                            }
-                                        }
-                                        _ => Err(SynError::new(
-                                            el.lit.span(),
-                                            "should be an integer literal",
-                                        )),
-                                    },
-                                    _ => Err(SynError::new(
-                                        ta.len.span(),
-                                        "should be an integer literal",
-                                    )),
-                                }
-                            }
-                            _ => Err(SynError::new(
-                                field.span(),
-                                "the field should be a fixed size array type",
-                            )),
-                        }
-                    }
+                syn::Fields::Named(named_fields) => named_fields
+                    .named
+                    .into_iter()
+                    .map(|field| {
+                        let member = syn::Member::Named(field.ident.clone().unwrap());
+                        (member, field)
+                    })
+                    .collect::<Vec<_>>(),
+                syn::Fields::Unnamed(unnamed_fields) => unnamed_fields
+                    .unnamed
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, field)| {
+                        let member = syn::Member::Unnamed(syn::Index {
+                            index: index as u32,
+                            span: field.span(),
+                        });
+                        (member, field)
+                    })
+                    .collect::<Vec<_>>(),
+                syn::Fields::Unit => {
+                    return Err(SynError::new(ident_span, "should have only one field"));
                 }
-                _ => Err(SynError::new(ident_span, "only support unnamed fields")),
             },
-            _ => Err(SynError::new(ident_span, "only support structs")),
+            _ => return Err(SynError::new(ident_span, "only support structs")),
+        };
+        let mut inner = None;
+        let mut candidates = Vec::new();
+        let mut skipped = Vec::new();
+        for (member, field) in &fields[..] {
+            match field_role(field)? {
+                Some(FieldRole::Inner) => {
+                    if inner.is_some() {
+                        return Err(SynError::new(
+                            field.span(),
+                            "only one field could be the inner array",
+                        ));
+                    }
+                    inner = Some((member, field));
+                }
+                Some(FieldRole::Skip) => {
+                    skipped.push((member.clone(), is_phantom_data(&field.ty)));
+                }
+                None if is_phantom_data(&field.ty) => skipped.push((member.clone(), true)),
+                None => candidates.push((member, field)),
+            }
         }
-    }
-}
-
-impl ::std::default::Default for NewArrayConf {
-    fn default() -> Self {
-        Self {
-            traits: Vec::new(),
-            traits_with_deps: Vec::new(),
+        let (member, field) = match (inner, &candidates[..]) {
+            (Some(inner), []) | (None, &[inner]) => inner,
+            (None, []) => {
+                return Err(SynError::new(
+                    ident_span,
+                    "should have a field of a fixed size array",
+                ));
+            }
+            (Some(_), [(_, field), ..]) | (None, [_, (_, field), ..]) => {
+                return Err(SynError::new(
+                    field.span(),
+                    "the extra field should be a `PhantomData` or be marked as \
+                    `#[new_array(skip)]`",
+                ));
+            }
+        };
+        match field.ty {
+            syn::Type::Array(ref ta) => {
+                let (elem, row) = match unwrap_type_group(&ta.elem) {
+                    syn::Type::Array(ref row) => {
+                        (parse_elem_type(&row.elem)?, Some(row.len.clone()))
+                    }
+                    elem => (parse_elem_type(elem)?, None),
+                };
+                let config = parse_attrs(&attrs[..], elem, row.is_some())?;
+                if let Some(span) = config.trait_span(DerivedTrait::FromRef) {
+                    if !is_repr_transparent(&attrs[..]) {
+                        return Err(SynError::new(
+                            span,
+                            "this attribute requires the struct to be `#[repr(transparent)]`",
+                        ));
+                    }
+                }
+                Ok(Self {
+                    vis,
+                    name: ident,
+                    generics,
+                    field: member.clone(),
+                    skipped,
+                    elem,
+                    row,
+                    length: ta.len.clone(),
+                    config,
+                })
+            }
+            _ => Err(SynError::new(
+                field.ty.span(),
+                "the field should be a fixed size array type",
+            )),
         }
     }
 }
 
 impl NewArrayConf {
-    fn apply_attrs(&mut self, meta: &syn::Meta) -> ParseResult<()> {
-        if !(meta.path().is_ident(ATTR_DERIVE) || meta.path().is_ident(ATTR_DERIVE_WITH_DEPS)) {
-            return Err(SynError::new(
+    fn apply_attrs(&mut self, meta: &syn::Meta, elem: ElemType, nested: bool) -> ParseResult<()> {
+        if meta.path().is_ident(ATTR_DERIVE) || meta.path().is_ident(ATTR_DERIVE_WITH_DEPS) {
+            self.apply_derive_attrs(meta, elem, nested)
+        } else if meta.path().is_ident(ATTR_DISPLAY) {
+            let value = parse_str_value(meta)?;
+            if self.display.is_some() {
+                return Err(SynError::new(
+                    meta.path().span(),
+                    "this attribute has been set twice",
+                ));
+            }
+            let style = DisplayStyle::parse_from_input(&value.value(), value.span())?;
+            self.display = Some((style, meta.span()));
+            Ok(())
+        } else if meta.path().is_ident(ATTR_EQ) {
+            let value = parse_str_value(meta)?;
+            if self.eq.is_some() {
+                return Err(SynError::new(
+                    meta.path().span(),
+                    "this attribute has been set twice",
+                ));
+            }
+            let mode = EqMode::parse_from_input(&value.value(), value.span())?;
+            if mode == EqMode::ConstantTime && (nested || elem != ElemType::U8) {
+                return Err(SynError::new(
+                    value.span(),
+                    "this comparison mode requires the element type to be `u8`",
+                ));
+            }
+            self.eq = Some((mode, meta.span()));
+            Ok(())
+        } else if meta.path().is_ident(ATTR_DEREF) {
+            let value = parse_str_value(meta)?;
+            if self.deref.is_some() {
+                return Err(SynError::new(
+                    meta.path().span(),
+                    "this attribute has been set twice",
+                ));
+            }
+            let target = DerefTarget::parse_from_input(&value.value(), value.span())?;
+            self.deref = Some((target, meta.span()));
+            Ok(())
+        } else if meta.path().is_ident(ATTR_UINT) {
+            let value = parse_str_value(meta)?;
+            if self.uint.is_some() {
+                return Err(SynError::new(
+                    meta.path().span(),
+                    "this attribute has been set twice",
+                ));
+            }
+            let endianness = Endianness::parse_from_input(&value.value(), value.span())?;
+            if nested || elem != ElemType::U8 {
+                return Err(SynError::new(
+                    value.span(),
+                    "this attribute requires the element type to be `u8`",
+                ));
+            }
+            self.uint = Some(endianness);
+            Ok(())
+        } else if meta.path().is_ident(ATTR_BIT_ORDER) {
+            let value = parse_str_value(meta)?;
+            if self.bit_order.is_some() {
+                return Err(SynError::new(
+                    meta.path().span(),
+                    "this attribute has been set twice",
+                ));
+            }
+            let order = BitOrder::parse_from_input(&value.value(), value.span())?;
+            self.bit_order = Some((order, meta.span()));
+            Ok(())
+        } else if meta.path().is_ident(ATTR_ENCODING) {
+            let value = parse_str_value(meta)?;
+            if self.encoding.is_some() {
+                return Err(SynError::new(
+                    meta.path().span(),
+                    "this attribute has been set twice",
+                ));
+            }
+            let encoding = Encoding::parse_from_input(&value.value(), value.span())?;
+            if nested || elem != ElemType::U8 {
+                return Err(SynError::new(
+                    value.span(),
+                    "this attribute requires the element type to be `u8`",
+                ));
+            }
+            self.encoding = Some((encoding, meta.span()));
+            Ok(())
+        } else if meta.path().is_ident(ATTR_HRP) {
+            let value = parse_str_value(meta)?;
+            if self.hrp.is_some() {
+                return Err(SynError::new(
+                    meta.path().span(),
+                    "this attribute has been set twice",
+                ));
+            }
+            let hrp = value.value();
+            if hrp.is_empty() || hrp.len() > 83 {
+                return Err(SynError::new(
+                    value.span(),
+                    "the human-readable part should have 1 to 83 characters",
+                ));
+            }
+            if !hrp.bytes().all(|chr| (33..=126).contains(&chr)) {
+                return Err(SynError::new(
+                    value.span(),
+                    "the human-readable part should only contain printable ASCII characters",
+                ));
+            }
+            if hrp.bytes().any(|chr| chr.is_ascii_uppercase()) {
+                return Err(SynError::new(
+                    value.span(),
+                    "the human-readable part should be in lowercase",
+                ));
+            }
+            self.hrp = Some((hrp, meta.span()));
+            Ok(())
+        } else if meta.path().is_ident(ATTR_METHODS) {
+            if let syn::Meta::Path(path) = meta {
+                if self.methods {
+                    return Err(SynError::new(
+                        path.span(),
+                        "this attribute has been set twice",
+                    ));
+                }
+                self.methods = true;
+                Ok(())
+            } else {
+                Err(SynError::new(
+                    meta.span(),
+                    "this attribute should be a path",
+                ))
+            }
+        } else {
+            let input = meta
+                .path()
+                .get_ident()
+                .map(ToString::to_string)
+                .unwrap_or_default();
+            Err(unknown_error(
                 meta.path().span(),
                 "this attribute was unknown",
-            ));
+                &input,
+                ATTRS.iter().copied(),
+            ))
         }
+    }
+
+    fn apply_derive_attrs(
+        &mut self,
+        meta: &syn::Meta,
+        elem: ElemType,
+        nested: bool,
+    ) -> ParseResult<()> {
         match meta {
             syn::Meta::Path(path) => Err(SynError::new(
                 path.span(),
@@ -250,10 +801,10 @@ impl NewArrayConf {
                     .as_ref()
                 {
                     ATTR_DERIVE => {
-                        self.update_derived_traits(&path_params)?;
+                        self.update_derived_traits(&path_params, elem, nested)?;
                     }
                     ATTR_DERIVE_WITH_DEPS => {
-                        self.update_derived_traits_with_deps(&path_params)?;
+                        self.update_derived_traits_with_deps(&path_params, elem, nested)?;
                     }
                     attr => {
                         return Err(SynError::new(
@@ -271,27 +822,52 @@ impl NewArrayConf {
         }
     }
 
-    fn update_derived_traits<'a>(&mut self, path_params: &[&syn::Path]) -> ParseResult<()> {
+    fn update_derived_traits(
+        &mut self,
+        path_params: &[&syn::Path],
+        elem: ElemType,
+        nested: bool,
+    ) -> ParseResult<()> {
         for p in path_params.iter() {
             let s = p
                 .get_ident()
                 .ok_or_else(|| SynError::new(p.span(), "this attribute should be a single ident"))?
                 .to_string();
             let dt = DerivedTrait::parse_from_input(&s, p.span())?;
-            if self.traits.iter().any(|tmp| tmp == &dt) {
+            if dt.requires_bytes() && (nested || elem != ElemType::U8) {
+                return Err(SynError::new(
+                    p.span(),
+                    "this attribute requires the element type to be `u8`",
+                ));
+            }
+            if dt == DerivedTrait::Bitwise && (nested || !elem.is_bitwise()) {
+                return Err(SynError::new(
+                    p.span(),
+                    "this attribute requires the element type to be an integer or `bool`",
+                ));
+            }
+            if dt.requires_total_eq() && elem.is_float() {
+                return Err(SynError::new(
+                    p.span(),
+                    "this attribute can't be derived for floating-point elements",
+                ));
+            }
+            if self.has_trait(dt) {
                 return Err(SynError::new(
                     p.span(),
                     "this attribute has already been set",
                 ));
             }
-            self.traits.push(dt);
+            self.traits.push((dt, p.span()));
         }
         Ok(())
     }
 
-    fn update_derived_traits_with_deps<'a>(
+    fn update_derived_traits_with_deps(
         &mut self,
         path_params: &[&syn::Path],
+        elem: ElemType,
+        nested: bool,
     ) -> ParseResult<()> {
         for p in path_params.iter() {
             let s = p
@@ -299,65 +875,429 @@ impl NewArrayConf {
                 .ok_or_else(|| SynError::new(p.span(), "this attribute should be a single ident"))?
                 .to_string();
             let dt = DerivedTraitWithDeps::parse_from_input(&s, p.span())?;
-            if self.traits_with_deps.iter().any(|tmp| tmp == &dt) {
+            if dt.requires_bytes() && (nested || elem != ElemType::U8) {
+                return Err(SynError::new(
+                    p.span(),
+                    "this attribute requires the element type to be `u8`",
+                ));
+            }
+            if self.has_trait_with_deps(dt) {
                 return Err(SynError::new(
                     p.span(),
                     "this attribute has already been set",
                 ));
             }
-            self.traits_with_deps.push(dt);
+            self.traits_with_deps.push((dt, p.span()));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn has_trait(&self, dt: DerivedTrait) -> bool {
+        self.trait_span(dt).is_some()
+    }
+
+    pub(crate) fn trait_span(&self, dt: DerivedTrait) -> Option<proc_macro2::Span> {
+        self.traits
+            .iter()
+            .find(|(tmp, _)| *tmp == dt)
+            .map(|(_, span)| *span)
+    }
+
+    pub(crate) fn has_trait_with_deps(&self, dt: DerivedTraitWithDeps) -> bool {
+        self.trait_with_deps_span(dt).is_some()
+    }
+
+    pub(crate) fn trait_with_deps_span(
+        &self,
+        dt: DerivedTraitWithDeps,
+    ) -> Option<proc_macro2::Span> {
+        self.traits_with_deps
+            .iter()
+            .find(|(tmp, _)| *tmp == dt)
+            .map(|(_, span)| *span)
+    }
+
+    pub(crate) fn eq_mode(&self) -> EqMode {
+        self.eq.map(|(mode, _)| mode).unwrap_or_default()
+    }
+
+    pub(crate) fn display_style(&self) -> DisplayStyle {
+        self.display.map(|(style, _)| style).unwrap_or_default()
+    }
+
+    pub(crate) fn deref_target(&self) -> DerefTarget {
+        self.deref.map(|(target, _)| target).unwrap_or_default()
+    }
+
+    pub(crate) fn bit_order(&self) -> BitOrder {
+        self.bit_order.map(|(order, _)| order).unwrap_or_default()
+    }
+
+    pub(crate) fn encoding(&self) -> Encoding {
+        self.encoding
+            .map(|(encoding, _)| encoding)
+            .unwrap_or_default()
+    }
+
+    // Check the conflicts between the attributes.
+    fn check(&self) -> ParseResult<()> {
+        // The options which only take effect on some traits.
+        let options = [
+            (self.eq.map(|(_, span)| span), DerivedTrait::PartialEq),
+            (self.deref.map(|(_, span)| span), DerivedTrait::Deref),
+            (self.bit_order.map(|(_, span)| span), DerivedTrait::Bits),
+        ];
+        for (span, dt) in &options {
+            match span {
+                Some(span) if !self.has_trait(*dt) => {
+                    return Err(SynError::new(
+                        *span,
+                        format!("this attribute requires `{}` to be derived", dt.name()),
+                    ));
+                }
+                _ => {}
+            }
+        }
+        if let Some((_, span)) = self.display {
+            if !self.has_trait_with_deps(DerivedTraitWithDeps::Display) {
+                return Err(SynError::new(
+                    span,
+                    "this attribute requires `Display` to be derived",
+                ));
+            }
+        }
+        if let Some((_, span)) = self.encoding {
+            let encoded = [
+                DerivedTraitWithDeps::Display,
+                DerivedTraitWithDeps::FromStr,
+                DerivedTraitWithDeps::Serialize,
+                DerivedTraitWithDeps::Deserialize,
+            ];
+            if !encoded.iter().any(|dt| self.has_trait_with_deps(*dt)) {
+                return Err(SynError::new(
+                    span,
+                    "this attribute requires `Display`, `FromStr`, `Serialize` or `Deserialize` \
+                    to be derived",
+                ));
+            }
+        }
+        if let Some((EqMode::ConstantTime, eq_span)) = self.eq {
+            let leaky = [
+                DerivedTrait::PartialOrd,
+                DerivedTrait::Ord,
+                DerivedTrait::Hash,
+            ];
+            for dt in &leaky {
+                if let Some(span) = self.trait_span(*dt) {
+                    let mut err = SynError::new(
+                        span,
+                        "this trait leaks the contents by timing, \
+                        it can't be derived for a constant-time comparison",
+                    );
+                    err.combine(SynError::new(
+                        eq_span,
+                        "the constant-time comparison is required here",
+                    ));
+                    return Err(err);
+                }
+            }
+        }
+        if let Some(copy_span) = self.trait_span(DerivedTrait::Copy) {
+            let destructor = self
+                .trait_span(DerivedTrait::Drop)
+                .or_else(|| self.trait_with_deps_span(DerivedTraitWithDeps::ZeroizeOnDrop));
+            if let Some(span) = destructor {
+                let mut err = SynError::new(
+                    copy_span,
+                    "`Copy` can't be derived for a type which implements `Drop`",
+                );
+                err.combine(SynError::new(span, "a destructor is required here"));
+                return Err(err);
+            }
+        }
+        if let Some((encoding, span)) = self.encoding {
+            if encoding.requires_hrp() && self.hrp.is_none() {
+                return Err(SynError::new(
+                    span,
+                    "this encoding requires the attribute `hrp`",
+                ));
+            }
+        }
+        if let Some((_, span)) = self.hrp {
+            if !self.encoding().requires_hrp() {
+                return Err(SynError::new(
+                    span,
+                    "this attribute requires a Bech32 encoding",
+                ));
+            }
+        }
+        if let (Some((encoding, encoding_span)), Some((_, display_span))) =
+            (self.encoding, self.display)
+        {
+            if encoding != Encoding::Hex {
+                let mut err = SynError::new(
+                    display_span,
+                    "this attribute only works with the hexadecimal encoding",
+                );
+                err.combine(SynError::new(
+                    encoding_span,
+                    "a different encoding is chosen here",
+                ));
+                return Err(err);
+            }
         }
         Ok(())
     }
 }
 
-fn parse_attrs(span: proc_macro2::Span, attrs: &[syn::Attribute]) -> ParseResult<NewArrayConf> {
+fn parse_elem_type(ty: &syn::Type) -> ParseResult<ElemType> {
+    match unwrap_type_group(ty) {
+        syn::Type::Path(ref tp) if tp.qself.is_none() => tp
+            .path
+            .get_ident()
+            .and_then(|id| ElemType::parse_from_input(&id.to_string()))
+            .ok_or_else(|| SynError::new(tp.path.span(), "the type should be a primitive type")),
+        _ => Err(SynError::new(
+            ty.span(),
+            "the type should be a primitive type or an array of it",
+        )),
+    }
+}
+
+// Types passed through `macro_rules!` are wrapped in invisible groups.
+fn unwrap_type_group(ty: &syn::Type) -> &syn::Type {
+    match ty {
+        syn::Type::Group(tg) => unwrap_type_group(&tg.elem),
+        _ => ty,
+    }
+}
+
+// The role of the field which is marked as `#[new_array(inner)]` or `#[new_array(skip)]`.
+fn field_role(field: &syn::Field) -> ParseResult<Option<FieldRole>> {
+    let mut role = None;
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident(ATTR_NAME))
+    {
+        let meta = attr.parse_meta()?;
+        let list = match meta {
+            syn::Meta::List(list) if !list.nested.is_empty() => list,
+            _ => {
+                return Err(SynError::new(
+                    meta.span(),
+                    "this field attribute should be `#[new_array(inner)]` or \
+                    `#[new_array(skip)]`",
+                ));
+            }
+        };
+        for nested_meta in list.nested.iter() {
+            let current = match nested_meta {
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident(ATTR_INNER) => {
+                    FieldRole::Inner
+                }
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident(ATTR_SKIP) => {
+                    FieldRole::Skip
+                }
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) => {
+                    let input = path
+                        .get_ident()
+                        .map(ToString::to_string)
+                        .unwrap_or_default();
+                    return Err(unknown_error(
+                        path.span(),
+                        "this field attribute was unknown",
+                        &input,
+                        [ATTR_INNER, ATTR_SKIP].iter().copied(),
+                    ));
+                }
+                _ => {
+                    return Err(SynError::new(
+                        nested_meta.span(),
+                        "this field attribute should be a path",
+                    ));
+                }
+            };
+            if role.is_some() {
+                return Err(SynError::new(
+                    nested_meta.span(),
+                    "only one of `inner` and `skip` could be set",
+                ));
+            }
+            role = Some(current);
+        }
+    }
+    Ok(role)
+}
+
+fn is_phantom_data(ty: &syn::Type) -> bool {
+    match unwrap_type_group(ty) {
+        syn::Type::Path(tp) if tp.qself.is_none() => tp
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident == "PhantomData")
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+fn is_repr_transparent(attrs: &[syn::Attribute]) -> bool {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("repr"))
+        .filter_map(|attr| attr.parse_meta().ok())
+        .any(|meta| match meta {
+            syn::Meta::List(list) => list.nested.iter().any(|nested_meta| match nested_meta {
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) => path.is_ident("transparent"),
+                _ => false,
+            }),
+            _ => false,
+        })
+}
+
+fn parse_str_value(meta: &syn::Meta) -> ParseResult<syn::LitStr> {
+    match meta {
+        syn::Meta::NameValue(name_value) => match name_value.lit {
+            syn::Lit::Str(ref value) => Ok(value.clone()),
+            ref lit => Err(SynError::new(
+                lit.span(),
+                "the value of this attribute should be a string literal",
+            )),
+        },
+        _ => Err(SynError::new(
+            meta.span(),
+            "this attribute should be a name-value pair",
+        )),
+    }
+}
+
+// The element type is the innermost one if the elements are rows of a nested array.
+fn parse_attrs(
+    attrs: &[syn::Attribute],
+    elem: ElemType,
+    nested: bool,
+) -> ParseResult<NewArrayConf> {
     let mut conf = NewArrayConf::default();
     for attr in attrs.iter() {
         if let syn::AttrStyle::Outer = attr.style {
-            let meta = attr
-                .parse_meta()
-                .map_err(|_| SynError::new(span, "failed to parse the attributes"))?;
-            match meta {
+            if !attr.path.is_ident(ATTR_NAME) {
+                continue;
+            }
+            match attr.parse_meta()? {
                 syn::Meta::Path(path) => {
-                    if path.is_ident(ATTR_NAME) {
+                    return Err(SynError::new(
+                        path.span(),
+                        "the attribute should not be a path",
+                    ));
+                }
+                syn::Meta::List(list) => {
+                    if list.nested.is_empty() {
                         return Err(SynError::new(
-                            path.span(),
-                            "the attribute should not be a path",
+                            list.span(),
+                            "this attribute should not be empty",
                         ));
                     }
-                }
-                syn::Meta::List(list) => {
-                    if list.path.is_ident(ATTR_NAME) {
-                        if list.nested.is_empty() {
-                            return Err(SynError::new(
-                                list.span(),
-                                "this attribute should not be empty",
-                            ));
-                        }
-                        for nested_meta in list.nested.iter() {
-                            match nested_meta {
-                                syn::NestedMeta::Meta(meta) => conf.apply_attrs(meta)?,
-                                syn::NestedMeta::Lit(lit) => {
-                                    return Err(SynError::new(
-                                        lit.span(),
-                                        "the attribute in nested meta should not be a literal",
-                                    ));
-                                }
+                    for nested_meta in list.nested.iter() {
+                        match nested_meta {
+                            syn::NestedMeta::Meta(meta) => conf.apply_attrs(meta, elem, nested)?,
+                            syn::NestedMeta::Lit(lit) => {
+                                return Err(SynError::new(
+                                    lit.span(),
+                                    "the attribute in nested meta should not be a literal",
+                                ));
                             }
                         }
                     }
                 }
                 syn::Meta::NameValue(name_value) => {
-                    if name_value.path.is_ident(ATTR_NAME) {
-                        return Err(SynError::new(
-                            name_value.span(),
-                            "the attribute should not be a name-value pair",
-                        ));
-                    }
+                    return Err(SynError::new(
+                        name_value.span(),
+                        "the attribute should not be a name-value pair",
+                    ));
                 }
             }
         }
     }
+    conf.check()?;
     Ok(conf)
 }
+
+// Append a suggestion to the message if the input looks like a misspelled candidate.
+fn unknown_error<'a>(
+    span: proc_macro2::Span,
+    message: &str,
+    input: &str,
+    candidates: impl Iterator<Item = &'a str>,
+) -> SynError {
+    if let Some((_, candidate)) = closest_candidate(input, candidates) {
+        SynError::new(span, format!("{}, did you mean `{}`?", message, candidate))
+    } else {
+        SynError::new(span, message)
+    }
+}
+
+// Searches the other list of traits too, so a misspelled trait is suggested with its wrapper.
+fn unknown_trait_error<'a>(
+    span: proc_macro2::Span,
+    input: &str,
+    names: impl Iterator<Item = &'a str>,
+    (other_attr, other_names): (&str, impl Iterator<Item = &'a str>),
+) -> SynError {
+    let message = "this attribute was unknown";
+    let suggestion = match (
+        closest_candidate(input, names),
+        closest_candidate(input, other_names),
+    ) {
+        (Some((distance, _)), Some((other_distance, candidate))) if other_distance < distance => {
+            Some(format!("{}({})", other_attr, candidate))
+        }
+        (Some((_, candidate)), _) => Some(candidate.to_owned()),
+        (None, Some((_, candidate))) => Some(format!("{}({})", other_attr, candidate)),
+        (None, None) => None,
+    };
+    if let Some(suggestion) = suggestion {
+        SynError::new(span, format!("{}, did you mean `{}`?", message, suggestion))
+    } else {
+        SynError::new(span, message)
+    }
+}
+
+fn closest_candidate<'a>(
+    input: &str,
+    candidates: impl Iterator<Item = &'a str>,
+) -> Option<(usize, &'a str)> {
+    // Allow one typo per three characters, as rustc does.
+    let threshold = input.chars().count().max(3) / 3;
+    candidates
+        .map(|candidate| (edit_distance(input, candidate), candidate))
+        .filter(|(distance, candidate)| {
+            *distance <= threshold || input.eq_ignore_ascii_case(candidate)
+        })
+        .min_by_key(|(distance, _)| *distance)
+}
+
+// The optimal string alignment distance, an edit distance which counts a transposition as one.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, v) in d[0].iter_mut().enumerate() {
+        *v = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}